@@ -25,6 +25,14 @@ var grass_texture2: texture_2d<f32>;
 var water_texture: texture_2d<f32>;
 @group(1) @binding(8) var water_texture_sampler: sampler;
 
+// x: 0 circle / 1 rectangle, y: radius or half width, z: half height, w: 1 if edges wrap
+@group(1) @binding(9)
+var<uniform> arena: vec4<f32>;
+
+// x: width of the band along the edge that is about to be lost to a shrinking arena
+@group(1) @binding(10)
+var<uniform> arena_warning: vec4<f32>;
+
 @fragment
 fn fragment(
     vertex_output: MeshVertexOutput,
@@ -45,21 +53,39 @@ fn fragment(
     let dt = textureSample(dirt_texture, dirt_color_sampler, p);
     var g2 = fbm_simplex_2d_seeded(p, 1, 2.5, 1.0, seed+1.0);
     var n = fbm_simplex_2d_seeded(p, 1, 1.8, 0.8, seed);
-    let water = vec4(0.0,0.0,0.0,1.0);
-
-    let distance = p.x*p.x + p.y*p.y;
-    let radius = 2.0;
-    let radius_st = 1.8;
-    let radius_st_sq = radius_st*radius_st;
-    let radiussq = radius*radius;
+    var water = vec4(0.0,0.0,0.0,1.0);
+    if (arena.w > 0.5){
+        // wrapping edges can be crossed, so they are drawn lighter
+        water = vec4(0.1,0.2,0.35,1.0);
+    }
 
     var water_seed = 1.0;
-    if (distance>radiussq){
-        water_seed = 0.0;
-    }else if (distance > radius_st_sq){
-        let nom = distance-radius_st_sq;
-        let demon = radiussq - radius_st_sq;
-        water_seed = 1.0 - (nom / demon);
+    // distance to the edge, in the same units as p
+    var edge = 0.0;
+    if (arena.x < 0.5){
+        let distance = p.x*p.x + p.y*p.y;
+        let radius = arena.y * 0.002;
+        edge = radius - sqrt(distance);
+        let radius_st = radius * 0.9;
+        let radius_st_sq = radius_st*radius_st;
+        let radiussq = radius*radius;
+
+        if (distance>radiussq){
+            water_seed = 0.0;
+        }else if (distance > radius_st_sq){
+            let nom = distance-radius_st_sq;
+            let demon = radiussq - radius_st_sq;
+            water_seed = 1.0 - (nom / demon);
+        }
+    }else{
+        let half_size = arena.yz * 0.002;
+        edge = min(half_size.x - abs(p.x), half_size.y - abs(p.y));
+        let band = 0.2;
+        if (edge < 0.0){
+            water_seed = 0.0;
+        }else if (edge < band){
+            water_seed = edge / band;
+        }
     }
 
     let ct4 = alphaBlend(vec4(ct.x, ct.y, ct.z, n), vec4(gt2.x, gt2.y, gt2.z, n), g2);
@@ -70,7 +96,11 @@ fn fragment(
     //     water_seed = 0.0;
     // }
     let blended_terrain = alphaBlend(dt4, ct4, n);
-    let blended = alphaBlend(blended_terrain, water, water_seed);
+    var blended = alphaBlend(blended_terrain, water, water_seed);
+
+    if (arena_warning.x > 0.0 && edge < arena_warning.x * 0.002){
+        blended = alphaBlend(vec4(0.8,0.1,0.1,1.0), blended, 0.35 * water_seed);
+    }
 
     return blended;
 }
@@ -0,0 +1,424 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bots::Bot,
+    game_mode::CurrentMatch,
+    networking::{send_for, ConnectionState, TransportMessage},
+    obstacles::{generate_obstacles, room_seed},
+    CellTag, Head, LastMoveId, MoveId, Moves, Player, SnakeTag,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ArenaShape {
+    Circle { radius: f32 },
+    Rectangle { half_size: Vec2 },
+}
+
+impl ArenaShape {
+    /// The same shape, grown or shrunk around the centre.
+    pub fn scaled(&self, factor: f32) -> Self {
+        match *self {
+            ArenaShape::Circle { radius } => ArenaShape::Circle {
+                radius: radius * factor,
+            },
+            ArenaShape::Rectangle { half_size } => ArenaShape::Rectangle {
+                half_size: half_size * factor,
+            },
+        }
+    }
+
+    /// Distance from the centre to the closest point of the edge.
+    pub fn inner_radius(&self) -> f32 {
+        match *self {
+            ArenaShape::Circle { radius } => radius,
+            ArenaShape::Rectangle { half_size } => half_size.min_element(),
+        }
+    }
+
+    /// Half the width and height of the box around the shape.
+    pub fn half_extents(&self) -> Vec2 {
+        match *self {
+            ArenaShape::Circle { radius } => Vec2::splat(radius),
+            ArenaShape::Rectangle { half_size } => half_size,
+        }
+    }
+}
+
+/// What happens to a snake whose head leaves the arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EdgeBehaviour {
+    /// The snake dies in the water around the arena.
+    #[default]
+    Lethal,
+    /// The head comes back in on the opposite side. Only rectangular arenas wrap.
+    Wrap,
+}
+
+impl EdgeBehaviour {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EdgeBehaviour::Lethal => "Lethal",
+            EdgeBehaviour::Wrap => "Wrap",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            EdgeBehaviour::Lethal => EdgeBehaviour::Wrap,
+            EdgeBehaviour::Wrap => EdgeBehaviour::Lethal,
+        }
+    }
+}
+
+/// Most portal pairs the host can ask for.
+pub const MAX_PORTAL_PAIRS: usize = 3;
+
+/// Bounds of the playing field and how its edges behave. Every peer needs the same arena.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct Arena {
+    pub shape: ArenaShape,
+    pub edge: EdgeBehaviour,
+    /// Number of linked portal pairs placed in the arena.
+    pub portal_pairs: usize,
+    /// Width of the band along the edge that is about to be lost to a shrinking arena.
+    pub warning_margin: f32,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Arena {
+            shape: ArenaShape::Circle { radius: 1000.0 },
+            edge: EdgeBehaviour::Lethal,
+            portal_pairs: 0,
+            warning_margin: 0.0,
+        }
+    }
+}
+
+impl Arena {
+    /// Whether `point` is inside the arena and at least `margin` away from its edge.
+    pub fn contains(&self, point: Vec2, margin: f32) -> bool {
+        match self.shape {
+            ArenaShape::Circle { radius } => point.length() < radius - margin,
+            ArenaShape::Rectangle { half_size } => {
+                point.x.abs() < half_size.x - margin && point.y.abs() < half_size.y - margin
+            }
+        }
+    }
+
+    pub fn wraps(&self) -> bool {
+        matches!(self.shape, ArenaShape::Rectangle { .. }) && self.edge == EdgeBehaviour::Wrap
+    }
+
+    /// Where a head that left the arena at `point` comes back in, if the edges wrap.
+    pub fn wrap_point(&self, point: Vec2) -> Option<Vec2> {
+        let ArenaShape::Rectangle { half_size } = self.shape else {
+            return None;
+        };
+        if self.edge != EdgeBehaviour::Wrap {
+            return None;
+        }
+        let mut wrapped = point;
+        if point.x > half_size.x {
+            wrapped.x -= 2.0 * half_size.x;
+        } else if point.x < -half_size.x {
+            wrapped.x += 2.0 * half_size.x;
+        }
+        if point.y > half_size.y {
+            wrapped.y -= 2.0 * half_size.y;
+        } else if point.y < -half_size.y {
+            wrapped.y += 2.0 * half_size.y;
+        }
+        (wrapped != point).then_some(wrapped)
+    }
+
+    /// A random point at least `margin` away from the edge.
+    pub fn random_point(&self, rng: &mut impl Rng, margin: f32) -> Vec2 {
+        match self.shape {
+            ArenaShape::Circle { radius } => {
+                let rad = rng.gen::<f32>() * (radius - margin).max(0.0);
+                let angle = rng.gen::<f32>() * std::f32::consts::PI * 2.0;
+                Vec2::from_angle(angle) * rad
+            }
+            ArenaShape::Rectangle { half_size } => {
+                let half_size = (half_size - Vec2::splat(margin)).max(Vec2::ZERO);
+                Vec2::new(
+                    (rng.gen::<f32>() * 2.0 - 1.0) * half_size.x,
+                    (rng.gen::<f32>() * 2.0 - 1.0) * half_size.y,
+                )
+            }
+        }
+    }
+
+    pub fn area(&self) -> f32 {
+        match self.shape {
+            ArenaShape::Circle { radius } => std::f32::consts::PI * radius * radius,
+            ArenaShape::Rectangle { half_size } => 4.0 * half_size.x * half_size.y,
+        }
+    }
+
+    /// Packs the arena for the terrain shader: shape, radius or half width, half height, wrap.
+    pub fn shader_params(&self) -> Vec4 {
+        let wraps = if self.wraps() { 1.0 } else { 0.0 };
+        match self.shape {
+            ArenaShape::Circle { radius } => Vec4::new(0.0, radius, radius, wraps),
+            ArenaShape::Rectangle { half_size } => Vec4::new(1.0, half_size.x, half_size.y, wraps),
+        }
+    }
+}
+
+/// One end of a portal pair. A head entering it comes out of the linked portal at `exit`.
+#[derive(Component)]
+pub struct Portal {
+    pub exit: Vec2,
+}
+
+pub const PORTAL_RADIUS: f32 = 30.0;
+
+const PORTAL_COLORS: [Color; 4] = [
+    Color::rgb(0.2, 0.5, 1.0),
+    Color::rgb(1.0, 0.55, 0.1),
+    Color::rgb(0.7, 0.3, 1.0),
+    Color::rgb(0.2, 0.9, 0.6),
+];
+
+/// Pairs up portal positions away from the centre, the edge and any obstacle.
+pub fn generate_portals(seed: u64, arena: &Arena) -> Vec<(Vec2, Vec2)> {
+    // Offset the seed so portals don't sit on the same spots the obstacles were tried at.
+    let mut rng = StdRng::seed_from_u64(seed ^ 0x706f_7274_616c);
+    let obstacles = generate_obstacles(seed, arena);
+    let mut placed: Vec<Vec2> = vec![];
+    let mut pairs = vec![];
+    for _ in 0..arena.portal_pairs {
+        let mut pair = vec![];
+        while pair.len() < 2 {
+            let mut found = None;
+            for _ in 0..20 {
+                let position = arena.random_point(&mut rng, 150.0);
+                let crowded = position.length() < 150.0
+                    || placed
+                        .iter()
+                        .any(|other| other.distance(position) < PORTAL_RADIUS * 8.0)
+                    || obstacles.iter().any(|obstacle| {
+                        obstacle.position.distance(position)
+                            < obstacle.shape.reach() + PORTAL_RADIUS * 3.0
+                    });
+                if !crowded {
+                    found = Some(position);
+                    break;
+                }
+            }
+            let Some(position) = found else {
+                break;
+            };
+            placed.push(position);
+            pair.push(position);
+        }
+        if let [a, b] = pair[..] {
+            pairs.push((a, b));
+        }
+    }
+    pairs
+}
+
+pub fn spawn_portals(
+    mut commands: Commands,
+    portals: Query<&Portal>,
+    arena: Res<Arena>,
+    connection_handler: Res<ConnectionState>,
+) {
+    if !portals.is_empty() {
+        return;
+    }
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let pairs = generate_portals(room_seed(&connection.room_id), &arena);
+    for (index, (a, b)) in pairs.into_iter().enumerate() {
+        let color = PORTAL_COLORS[index % PORTAL_COLORS.len()];
+        for (entry, exit) in [(a, b), (b, a)] {
+            commands
+                .spawn((
+                    Portal { exit },
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(Vec2::splat(PORTAL_RADIUS * 1.6)),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(entry.extend(0.4)),
+                        ..default()
+                    },
+                ))
+                .with_children(|portal| {
+                    portal.spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(0.05, 0.05, 0.1, 0.9),
+                            custom_size: Some(Vec2::splat(PORTAL_RADIUS)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(0.0, 0.0, 0.01)
+                            .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+                        ..default()
+                    });
+                });
+        }
+    }
+}
+
+pub fn animate_portals(time: Res<Time>, mut portals: Query<&mut Transform, With<Portal>>) {
+    for mut transform in portals.iter_mut() {
+        transform.rotate_z(2.0 * time.delta_seconds());
+    }
+}
+
+/// Plans a jump for the heads of the players at this machine, and of the bots it hosts,
+/// when they cross an edge that doesn't kill or enter a portal.
+///
+/// The jump is recorded as a move with a target point, so the body and the peers follow
+/// it cell by cell just like a turn.
+pub fn teleport_heads(
+    arena: Res<Arena>,
+    current_match: Res<CurrentMatch>,
+    portals: Query<(&Transform, &Portal), Without<CellTag>>,
+    mut snakes: Query<
+        (&SnakeTag, &Children, &mut LastMoveId, &mut Moves),
+        Or<(With<Player>, With<Bot>)>,
+    >,
+    heads: Query<(&Transform, &crate::Direction, &MoveId), With<Head>>,
+    connection_handler: Res<ConnectionState>,
+    time: Res<Time>,
+) {
+    let wraps = !current_match.settings.mode.rules().edge_kills(&arena);
+    for (tag, children, mut last_move, mut moves) in snakes.iter_mut() {
+        let Some((head, direction, move_id)) =
+            children.iter().find_map(|cell| heads.get(*cell).ok())
+        else {
+            continue;
+        };
+        // Wait until the head went through the last jump before planning another one.
+        if move_id.0 != last_move.0 {
+            continue;
+        }
+        let point = head.translation.truncate() + direction.0;
+        let wrapped = wraps.then(|| arena.wrap_point(point)).flatten();
+        let target = wrapped.or_else(|| {
+            portals.iter().find_map(|(transform, portal)| {
+                let offset = point - transform.translation.truncate();
+                if offset.length() >= PORTAL_RADIUS {
+                    return None;
+                }
+                // Come out just past the other portal, keeping the sideways offset.
+                let across = offset - direction.0 * offset.dot(direction.0);
+                Some(portal.exit + across + direction.0 * (PORTAL_RADIUS + 2.0))
+            })
+        });
+        let Some(target) = target else {
+            continue;
+        };
+
+        last_move.0 += 1;
+        let _move = (
+            last_move.0,
+            point.extend(head.translation.z),
+            direction.clone(),
+            Some(target.extend(head.translation.z)),
+        );
+        moves.moves.push(_move.clone());
+        let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+            continue;
+        };
+        if let Some(user_id) = connection.snake_owner(tag) {
+            send_for(
+                connection,
+                user_id,
+                TransportMessage::AddMove(time.elapsed_seconds(), _move),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_keeps_the_outline() {
+        let circle = ArenaShape::Circle { radius: 1000.0 }.scaled(0.5);
+        assert_eq!(circle, ArenaShape::Circle { radius: 500.0 });
+        let rectangle = ArenaShape::Rectangle {
+            half_size: Vec2::new(1100.0, 750.0),
+        }
+        .scaled(2.0);
+        assert_eq!(
+            rectangle,
+            ArenaShape::Rectangle {
+                half_size: Vec2::new(2200.0, 1500.0)
+            }
+        );
+        assert_eq!(rectangle.inner_radius(), 1500.0);
+    }
+
+    #[test]
+    fn only_rectangles_wrap() {
+        let mut arena = Arena {
+            edge: EdgeBehaviour::Wrap,
+            ..default()
+        };
+        assert_eq!(arena.wrap_point(Vec2::new(1200.0, 0.0)), None);
+        arena.shape = ArenaShape::Rectangle {
+            half_size: Vec2::new(100.0, 50.0),
+        };
+        assert_eq!(
+            arena.wrap_point(Vec2::new(110.0, 0.0)),
+            Some(Vec2::new(-90.0, 0.0))
+        );
+        assert_eq!(arena.wrap_point(Vec2::new(50.0, 0.0)), None);
+    }
+
+    #[test]
+    fn bots_jump_through_wrapping_edges() {
+        let mut world = World::new();
+        world.insert_resource(Arena {
+            shape: ArenaShape::Rectangle {
+                half_size: Vec2::new(100.0, 50.0),
+            },
+            edge: EdgeBehaviour::Wrap,
+            ..default()
+        });
+        world.insert_resource(CurrentMatch::default());
+        world.insert_resource(ConnectionState::NotConnected);
+        world.insert_resource(Time::default());
+        let head = world
+            .spawn((
+                Head,
+                Transform::from_xyz(100.0, 0.0, 1.0),
+                crate::Direction(Vec2::X),
+                MoveId(0),
+            ))
+            .id();
+        let snake = world
+            .spawn((
+                SnakeTag::OtherPlayerSnake(crate::bots::BOT_ID_BASE),
+                LastMoveId(0),
+                Moves { moves: vec![] },
+                Bot::new(
+                    crate::bots::BOT_ID_BASE,
+                    crate::bots::BotDifficulty::Normal,
+                    head,
+                ),
+            ))
+            .push_children(&[head])
+            .id();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(teleport_heads);
+        schedule.run(&mut world);
+
+        let moves = &world.get::<Moves>(snake).unwrap().moves;
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].3, Some(Vec3::new(-99.0, 0.0, 1.0)));
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+
+use crate::{
+    arena::{Arena, ArenaShape},
+    game_mode::CurrentMatch,
+    networking::GameClock,
+    Head, Player,
+};
+
+/// Seconds after the start before the arena begins to close in.
+const SHRINK_DELAY: f32 = 30.0;
+
+/// Seconds it takes the arena to close in all the way.
+const SHRINK_DURATION: f32 = 150.0;
+
+/// Size of the arena once it stopped shrinking, relative to where it started.
+const FINAL_SCALE: f32 = 0.15;
+
+/// How far ahead the warning zone shows where the edge is going to be.
+const WARNING_LEAD: f32 = 10.0;
+
+/// Shape of the arena before it started shrinking, restored once the match is over.
+#[derive(Resource)]
+pub struct ShrinkingArena {
+    base: ArenaShape,
+}
+
+/// Arena scale `elapsed` seconds into the match.
+fn zone_scale(elapsed: f32) -> f32 {
+    let progress = ((elapsed - SHRINK_DELAY) / SHRINK_DURATION).clamp(0.0, 1.0);
+    1.0 - progress * (1.0 - FINAL_SCALE)
+}
+
+pub fn setup_battle_royale(
+    mut commands: Commands,
+    current_match: Res<CurrentMatch>,
+    arena: Res<Arena>,
+) {
+    if !current_match.settings.mode.rules().shrinks_arena() {
+        return;
+    }
+    commands.insert_resource(ShrinkingArena { base: arena.shape });
+    commands.spawn((
+        ZoneStatusText,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: Color::rgb(0.9, 0.9, 0.9),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.),
+            left: Val::Percent(40.),
+            ..default()
+        }),
+    ));
+}
+
+pub fn clean_battle_royale(
+    mut commands: Commands,
+    shrinking: Option<Res<ShrinkingArena>>,
+    mut arena: ResMut<Arena>,
+    hud: Query<Entity, With<ZoneStatusText>>,
+) {
+    if let Some(shrinking) = shrinking {
+        arena.shape = shrinking.base;
+        arena.warning_margin = 0.0;
+        commands.remove_resource::<ShrinkingArena>();
+    }
+    for entity in hud.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Closes the arena in on the schedule every peer derives from the host's start time.
+pub fn shrink_arena(
+    current_match: Res<CurrentMatch>,
+    shrinking: Option<Res<ShrinkingArena>>,
+    clock: Res<GameClock>,
+    time: Res<Time>,
+    mut arena: ResMut<Arena>,
+) {
+    let (Some(shrinking), Some(started_at)) = (shrinking, current_match.started_at) else {
+        return;
+    };
+    let elapsed = clock.now(&time) - started_at;
+    let scale = zone_scale(elapsed);
+    let shape = shrinking.base.scaled(scale);
+    let warning_margin =
+        shrinking.base.inner_radius() * (scale - zone_scale(elapsed + WARNING_LEAD));
+    if arena.shape != shape || arena.warning_margin != warning_margin {
+        arena.shape = shape;
+        arena.warning_margin = warning_margin;
+    }
+}
+
+#[derive(Component)]
+pub struct ZoneStatusText;
+
+pub fn display_zone_status(
+    current_match: Res<CurrentMatch>,
+    arena: Res<Arena>,
+    clock: Res<GameClock>,
+    time: Res<Time>,
+    head: Query<(&Parent, &Transform), With<Head>>,
+    players: Query<(Entity, &Player)>,
+    mut status: Query<&mut Text, With<ZoneStatusText>>,
+) {
+    let (Ok(mut text), Some(started_at)) = (status.get_single_mut(), current_match.started_at)
+    else {
+        return;
+    };
+    let elapsed = clock.now(&time) - started_at;
+    // Only the owner of the machine reads the status line.
+    let owner = players
+        .iter()
+        .find(|(_, player)| player.keys.is_none())
+        .map(|(snake, _)| snake);
+    let in_warning_zone = owner.and_then(|player| {
+        head.iter()
+            .find(|(parent, _)| parent.get() == player)
+            .map(|(_, head)| {
+                arena.warning_margin > 0.0
+                    && !arena.contains(head.translation.truncate(), arena.warning_margin)
+            })
+    });
+    let (status, color) = if in_warning_zone == Some(true) {
+        (
+            "Get back inside the zone!".to_string(),
+            Color::rgb(1.0, 0.3, 0.2),
+        )
+    } else if elapsed < SHRINK_DELAY {
+        (
+            format!("Zone closes in {:.0}s", (SHRINK_DELAY - elapsed).ceil()),
+            Color::rgb(0.9, 0.9, 0.9),
+        )
+    } else if elapsed < SHRINK_DELAY + SHRINK_DURATION {
+        ("Zone closing".to_string(), Color::rgb(1.0, 0.75, 0.2))
+    } else {
+        ("Final zone".to_string(), Color::rgb(1.0, 0.75, 0.2))
+    };
+    if let Some(section) = text.sections.first_mut() {
+        if section.value != status {
+            section.value = status;
+            section.style.color = color;
+        }
+    }
+}
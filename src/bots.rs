@@ -0,0 +1,678 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::Arena,
+    effects::{ActiveEffects, EffectKind},
+    food::{eat_food, new_snake_cell, Food, FoodKind},
+    game_mode::{BodyCollisions, CurrentMatch},
+    networking::{
+        player_color, send_for, snake_details, ConnectionHandler, ConnectionState, PlayerProp,
+        SendMessage, TransportMessage,
+    },
+    obstacles::Obstacle,
+    profile::Profile,
+    snek::{pick_spawn, KillSnake, SPAWN_MARGIN},
+    teams::are_teammates,
+    CellTag, Direction, GameConfig, Host, LastMoveId, MoveId, Moves, Snake, SnakeSpeed, SnakeTag,
+    Tail,
+};
+
+/// Bots get user ids from here up, well clear of the ones the relay hands out.
+pub const BOT_ID_BASE: u32 = 1 << 31;
+
+/// Seconds a bot sits out after dying, when the mode lets snakes respawn.
+const BOT_RESPAWN_SECONDS: f32 = 3.0;
+
+/// How often the host tells everyone where its bots are.
+const BOT_SYNC_SECONDS: f32 = 0.5;
+
+/// Bots start thinking about cutting a snake off once its head is this close.
+const HUNT_RANGE: f32 = 400.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BotDifficulty {
+    /// Wanders towards food and only looks a few cells ahead.
+    Easy,
+    /// Goes for food and steers clear of whatever is in the way.
+    Normal,
+    /// Looks further ahead and tries to cut other snakes off.
+    Hard,
+}
+
+impl BotDifficulty {
+    pub const ALL: [BotDifficulty; 3] = [
+        BotDifficulty::Easy,
+        BotDifficulty::Normal,
+        BotDifficulty::Hard,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BotDifficulty::Easy => "Easy",
+            BotDifficulty::Normal => "Normal",
+            BotDifficulty::Hard => "Hard",
+        }
+    }
+
+    /// Seconds between decisions.
+    fn reaction(&self) -> f32 {
+        match self {
+            BotDifficulty::Easy => 0.5,
+            BotDifficulty::Normal => 0.25,
+            BotDifficulty::Hard => 0.12,
+        }
+    }
+
+    /// How far ahead the bot checks for something to run into.
+    fn lookahead(&self) -> f32 {
+        match self {
+            BotDifficulty::Easy => 60.0,
+            BotDifficulty::Normal => 150.0,
+            BotDifficulty::Hard => 250.0,
+        }
+    }
+
+    /// Chance a decision ignores what's ahead.
+    fn blunder_chance(&self) -> f64 {
+        match self {
+            BotDifficulty::Easy => 0.2,
+            BotDifficulty::Normal => 0.03,
+            BotDifficulty::Hard => 0.0,
+        }
+    }
+}
+
+pub fn is_bot(user_id: u32) -> bool {
+    user_id >= BOT_ID_BASE
+}
+
+/// The host's brain for one bot snake.
+#[derive(Component)]
+pub struct Bot {
+    pub user_id: u32,
+    pub difficulty: BotDifficulty,
+    pub head: Entity,
+    think: Timer,
+}
+
+impl Bot {
+    pub fn new(user_id: u32, difficulty: BotDifficulty, head: Entity) -> Self {
+        Bot {
+            user_id,
+            difficulty,
+            head,
+            think: Timer::from_seconds(difficulty.reaction(), TimerMode::Repeating),
+        }
+    }
+}
+
+/// Collider in front of a bot's head, the bot counterpart of [`HeadSensor`](crate::HeadSensor).
+#[derive(Component)]
+pub struct BotSensor;
+
+/// Which bots have been in the current match, and when the dead ones went down.
+#[derive(Resource, Default)]
+pub struct BotLives {
+    spawned: Vec<u32>,
+    died_at: Vec<(u32, f32)>,
+}
+
+pub fn bot_roster(connection: &ConnectionHandler) -> Vec<(u32, BotDifficulty)> {
+    connection
+        .players
+        .iter()
+        .filter_map(|player| Some((player.user_id, player.bot?)))
+        .collect()
+}
+
+/// Brings the bots in `players` in line with the host's `roster`, returning the ids of the
+/// bots that were dropped.
+pub fn sync_bot_roster(players: &mut Vec<PlayerProp>, roster: &[(u32, BotDifficulty)]) -> Vec<u32> {
+    let removed: Vec<u32> = players
+        .iter()
+        .filter(|player| {
+            player.bot.is_some() && !roster.iter().any(|(id, _)| *id == player.user_id)
+        })
+        .map(|player| player.user_id)
+        .collect();
+    players.retain(|player| !removed.contains(&player.user_id));
+    for (user_id, difficulty) in roster.iter() {
+        if !players.iter().any(|player| player.user_id == *user_id) {
+            players.push(new_bot(*user_id, *difficulty));
+        }
+    }
+    removed
+}
+
+fn new_bot(user_id: u32, difficulty: BotDifficulty) -> PlayerProp {
+    PlayerProp {
+        last_update_time: None,
+        start_time: None,
+        user_id,
+        color: player_color(user_id),
+        score: 0,
+        highest_score: 0,
+        team: None,
+        bot: Some(difficulty),
+        seat: None,
+        spectator: false,
+        ready: false,
+        profile: Profile::default(),
+    }
+}
+
+/// Adds a bot to the room on the host and tells everyone.
+pub fn add_bot(connection: &mut ConnectionHandler, difficulty: BotDifficulty) {
+    let user_id = connection
+        .players
+        .iter()
+        .map(|player| player.user_id)
+        .filter(|id| is_bot(*id))
+        .max()
+        .map_or(BOT_ID_BASE, |id| id + 1);
+    connection.players.push(new_bot(user_id, difficulty));
+    send_bot_roster(connection);
+}
+
+/// Takes the most recently added bot out of the room on the host and tells everyone.
+pub fn remove_bot(connection: &mut ConnectionHandler) {
+    let Some(index) = connection
+        .players
+        .iter()
+        .rposition(|player| player.bot.is_some())
+    else {
+        return;
+    };
+    connection.players.remove(index);
+    send_bot_roster(connection);
+}
+
+fn send_bot_roster(connection: &ConnectionHandler) {
+    if let Err(err) = connection
+        .sender
+        .send(SendMessage::TransportMessage(TransportMessage::Bots(
+            bot_roster(connection),
+        )))
+    {
+        warn!("{err:?}")
+    }
+}
+
+pub fn reset_bot_lives(mut lives: ResMut<BotLives>) {
+    *lives = BotLives::default();
+}
+
+/// Spawns a snake for every bot that doesn't have one and is due to (re)spawn.
+pub fn spawn_bots(
+    mut commands: Commands,
+    host: Query<&Host>,
+    config: Res<GameConfig>,
+    arena: Res<Arena>,
+    current_match: Res<CurrentMatch>,
+    connection_handler: Res<ConnectionState>,
+    snakes: Query<(Entity, &SnakeTag, Option<&Bot>)>,
+    cells: Query<&GlobalTransform, With<CellTag>>,
+    obstacles: Query<&GlobalTransform, With<Obstacle>>,
+    mut lives: ResMut<BotLives>,
+    time: Res<Time>,
+) {
+    if host.is_empty() {
+        return;
+    }
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let rules = current_match.settings.mode.rules();
+    let now = time.elapsed_seconds();
+    for player in connection.players.iter() {
+        let Some(difficulty) = player.bot else {
+            continue;
+        };
+        let user_id = player.user_id;
+        match snakes
+            .iter()
+            .find(|(_, tag, _)| **tag == SnakeTag::OtherPlayerSnake(user_id))
+        {
+            Some((_, _, Some(_))) => continue,
+            // We just took over as host, the snake was run by the old one.
+            Some((snake, _, None)) => {
+                commands.entity(snake).despawn_recursive();
+                send_for(connection, user_id, TransportMessage::KillSnake);
+                continue;
+            }
+            None => {}
+        }
+        if lives.spawned.contains(&user_id) {
+            let died_at = match lives.died_at.iter().find(|(id, _)| *id == user_id) {
+                Some((_, died_at)) => *died_at,
+                None => {
+                    lives.died_at.push((user_id, now));
+                    now
+                }
+            };
+            if !rules.allows_respawn() || now - died_at < BOT_RESPAWN_SECONDS {
+                continue;
+            }
+        }
+        lives.spawned.retain(|id| *id != user_id);
+        lives.spawned.push(user_id);
+        lives.died_at.retain(|(id, _)| *id != user_id);
+
+        let hazards: Vec<Vec2> = cells
+            .iter()
+            .chain(obstacles.iter())
+            .map(|transform| transform.translation().truncate())
+            .collect();
+        let (position, direction) = pick_spawn(&arena, rules, SPAWN_MARGIN, &hazards);
+        let cell_size = Vec2::new(config.cell_size.0, config.cell_size.1);
+        let color = player.snake_color();
+        let cell_at = |index: f32| {
+            new_snake_cell(
+                &config,
+                color,
+                Transform::from_translation((position - direction * cell_size * index).extend(1.)),
+                direction,
+                0,
+            )
+        };
+
+        let mut effects = ActiveEffects::default();
+        if config.spawn_protection > 0.0 {
+            effects.add(EffectKind::SpawnProtection, config.spawn_protection);
+        }
+        let snake = commands
+            .spawn(Snake {
+                tag: SnakeTag::OtherPlayerSnake(user_id),
+                spatial: Default::default(),
+                lastmove: LastMoveId(0),
+                moves: Moves { moves: vec![] },
+                effects,
+                speed: SnakeSpeed(config.speed),
+            })
+            .id();
+        let head = commands
+            .spawn((cell_at(0.0), CollisionGroups::new(Group::NONE, Group::NONE)))
+            .with_children(|head| {
+                head.spawn((
+                    BotSensor,
+                    sensor_collider(&config, direction),
+                    RigidBody::KinematicPositionBased,
+                    Ccd::enabled(),
+                    ActiveCollisionTypes::all(),
+                    ActiveEvents::COLLISION_EVENTS,
+                    TransformBundle::from_transform(Transform::from_translation(
+                        (direction * cell_size / 2.0).extend(0.0),
+                    )),
+                ));
+            })
+            .id();
+        let body = commands
+            .spawn((cell_at(1.0), CollisionGroups::new(Group::NONE, Group::NONE)))
+            .id();
+        let tail = commands.spawn((cell_at(2.0), Tail)).id();
+        commands
+            .entity(snake)
+            .push_children(&[head, body, tail])
+            .insert(Bot::new(user_id, difficulty, head));
+    }
+}
+
+fn sensor_collider(config: &GameConfig, direction: Vec2) -> Collider {
+    if direction.x != 0.0 {
+        Collider::cuboid(1.0, config.cell_size.1 / 2.0)
+    } else {
+        Collider::cuboid(config.cell_size.0 / 2.0, 1.0)
+    }
+}
+
+/// Turns bots towards food, or other snakes' paths, while keeping them out of trouble.
+pub fn steer_bots(
+    mut bots: Query<(&mut Bot, &mut Moves, &mut LastMoveId, &Children)>,
+    heads: Query<(&Transform, &Direction), With<CellTag>>,
+    mut sensors: Query<
+        (&Parent, &mut Transform, &mut Collider),
+        (With<BotSensor>, Without<CellTag>),
+    >,
+    snakes: Query<&Children, With<SnakeTag>>,
+    food: Query<&Transform, (With<Food>, Without<CellTag>, Without<BotSensor>)>,
+    food_entities: Query<Entity, With<Food>>,
+    rapier_context: Res<RapierContext>,
+    arena: Res<Arena>,
+    current_match: Res<CurrentMatch>,
+    config: Res<GameConfig>,
+    connection_handler: Res<ConnectionState>,
+    time: Res<Time>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    // Heads go through a wrapping edge like everyone else's, so only a lethal one is a wall.
+    let edge_kills = current_match.settings.mode.rules().edge_kills(&arena);
+    let mut rng = rand::thread_rng();
+    // Heads worth cutting off: every snake's, players and bots alike.
+    let prey: Vec<(Entity, Vec2, Vec2)> = snakes
+        .iter()
+        .filter_map(|cells| {
+            cells.iter().find_map(|cell| {
+                heads.get(*cell).ok().map(|(transform, direction)| {
+                    (*cell, transform.translation.truncate(), direction.0)
+                })
+            })
+        })
+        .collect();
+    for (mut bot, mut moves, mut last_move, children) in bots.iter_mut() {
+        let difficulty = bot.difficulty;
+        bot.think.tick(time.delta());
+        if !bot.think.just_finished() {
+            continue;
+        }
+        let Ok((head, head_direction)) = heads.get(bot.head) else {
+            continue;
+        };
+        let position = head.translation.truncate();
+        let current = head_direction.0;
+
+        // The first few cells can't be run into anyway.
+        let own_neck: Vec<Entity> = children.iter().take(3).copied().collect();
+        let ignored = |entity: Entity| {
+            food_entities.contains(entity)
+                || own_neck.contains(&entity)
+                || sensors
+                    .get(entity)
+                    .is_ok_and(|(parent, ..)| parent.get() == bot.head)
+        };
+        let lookahead = difficulty.lookahead();
+        let room_ahead = |direction: Vec2| {
+            let blocked = rapier_context
+                .cast_ray(
+                    position + direction * config.cell_size.0 / 2.0,
+                    direction,
+                    lookahead,
+                    true,
+                    QueryFilter::new().predicate(&|entity| !ignored(entity)),
+                )
+                .map_or(lookahead, |(_, distance)| distance);
+            if !edge_kills {
+                return blocked;
+            }
+            let mut inside = 0.0;
+            while inside < blocked && arena.contains(position + direction * inside, 0.0) {
+                inside += config.cell_size.0;
+            }
+            blocked.min(inside)
+        };
+
+        let target = match difficulty {
+            // Aim for the spot just ahead of the closest snake's head.
+            BotDifficulty::Hard => prey
+                .iter()
+                .filter(|(entity, ..)| *entity != bot.head)
+                .map(|(_, head, direction)| *head + *direction * config.cell_size.0 * 4.0)
+                .filter(|point| point.distance(position) < HUNT_RANGE)
+                .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position))),
+            _ => None,
+        }
+        .or_else(|| {
+            food.iter()
+                .map(|transform| transform.translation.truncate())
+                .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)))
+        });
+        let pull = |direction: Vec2| {
+            target.map_or(0.0, |target| {
+                (target - position).normalize_or_zero().dot(direction)
+            })
+        };
+
+        let careless = rng.gen_bool(difficulty.blunder_chance());
+        let options = [current, current.perp(), -current.perp()];
+        let Some(direction) = options.into_iter().max_by(|a, b| {
+            let score = |direction: Vec2| {
+                let room = if careless {
+                    lookahead
+                } else {
+                    room_ahead(direction)
+                };
+                // Anything clear all the way beats a closer wall, then follow the target.
+                let mut score = room + pull(direction);
+                if direction == current {
+                    score += 0.1;
+                }
+                score
+            };
+            score(*a).total_cmp(&score(*b))
+        }) else {
+            continue;
+        };
+        if direction == current {
+            continue;
+        }
+
+        turn_bot(
+            connection,
+            &bot,
+            &mut moves,
+            &mut last_move,
+            head,
+            current,
+            direction,
+            &mut sensors,
+            &config,
+            &time,
+        );
+    }
+}
+
+/// Turns the snake of `bot` from `current` to `direction` at its head and tells everyone.
+fn turn_bot(
+    connection: &ConnectionHandler,
+    bot: &Bot,
+    moves: &mut Moves,
+    last_move: &mut LastMoveId,
+    head: &Transform,
+    current: Vec2,
+    direction: Vec2,
+    sensors: &mut Query<
+        (&Parent, &mut Transform, &mut Collider),
+        (With<BotSensor>, Without<CellTag>),
+    >,
+    config: &GameConfig,
+    time: &Time,
+) {
+    last_move.0 += 1;
+    let _move = (
+        last_move.0,
+        head.translation + current.extend(0.0),
+        Direction(direction),
+        None,
+    );
+    moves.moves.push(_move.clone());
+    send_for(
+        connection,
+        bot.user_id,
+        TransportMessage::AddMove(time.elapsed_seconds(), _move),
+    );
+    for (parent, mut transform, mut collider) in sensors.iter_mut() {
+        if parent.get() == bot.head {
+            *collider = sensor_collider(config, direction);
+            transform.translation =
+                (direction * Vec2::new(config.cell_size.0, config.cell_size.1) / 2.0).extend(0.0);
+        }
+    }
+}
+
+/// Eating and dying for bots, the host-side counterpart of
+/// [`handle_food_collision`](crate::food::handle_food_collision).
+pub fn bot_collisions(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    sensors: Query<&Parent, With<BotSensor>>,
+    heads: Query<&Parent, With<CellTag>>,
+    mut bots: Query<(&Bot, &SnakeTag, &Children, &mut ActiveEffects, &mut Moves)>,
+    other_snakes: Query<(&SnakeTag, &ActiveEffects), Without<Bot>>,
+    food: Query<(&Food, &FoodKind)>,
+    obstacles: Query<Entity, With<Obstacle>>,
+    tail: Query<(&Parent, &Transform, &Direction, &MoveId, Entity), With<Tail>>,
+    cell_move_ids: Query<&MoveId, With<CellTag>>,
+    config: Res<GameConfig>,
+    current_match: Res<CurrentMatch>,
+    connection_handler: Res<ConnectionState>,
+    mut snake_kill_writer: EventWriter<KillSnake>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    for collision_event in collision_events.iter() {
+        let CollisionEvent::Started(a, b, _flags) = collision_event else {
+            continue;
+        };
+        let (sensor, other) = match (sensors.get(*a), sensors.get(*b)) {
+            (Ok(sensor), _) => (sensor, *b),
+            (_, Ok(sensor)) => (sensor, *a),
+            _ => continue,
+        };
+        let Ok(snake) = heads.get(sensor.get()).map(|head| head.get()) else {
+            continue;
+        };
+        let Ok((bot, ..)) = bots.get(snake) else {
+            continue;
+        };
+        let user_id = bot.user_id;
+
+        if let Ok((food, kind)) = food.get(other) {
+            commands.entity(other).despawn_recursive();
+            if let Err(err) = connection.sender.send(SendMessage::TransportMessage(
+                TransportMessage::DespawnFood(food.0),
+            )) {
+                warn!("{err:?}")
+            }
+            let Ok((_, _, children, mut effects, mut moves)) = bots.get_mut(snake) else {
+                continue;
+            };
+            let Some(player) = connection.players.iter().find(|p| p.user_id == user_id) else {
+                continue;
+            };
+            let effects_changed = eat_food(
+                &mut commands,
+                &config,
+                *kind,
+                player.snake_color(),
+                (snake, children, &mut effects, &mut moves),
+                &tail,
+                &cell_move_ids,
+            );
+            if effects_changed {
+                send_for(
+                    connection,
+                    user_id,
+                    TransportMessage::EffectsChanged(effects.clone()),
+                );
+            }
+            continue;
+        }
+
+        let lethal = if let Ok(body_snake) = heads.get(other).map(|cell| cell.get()) {
+            if current_match.settings.bodies == BodyCollisions::Harmless {
+                false
+            } else if body_snake == snake {
+                let Ok((_, _, children, ..)) = bots.get(snake) else {
+                    continue;
+                };
+                !children.iter().take(3).any(|cell| *cell == other)
+            } else {
+                let body = other_snakes.get(body_snake).ok().or_else(|| {
+                    bots.get(body_snake)
+                        .ok()
+                        .map(|(_, tag, _, effects, _)| (tag, effects))
+                });
+                body.is_some_and(|(tag, body_effects)| {
+                    let body_id = match tag {
+                        SnakeTag::SelfPlayerSnake => connection.self_id,
+                        SnakeTag::OtherPlayerSnake(id) => Some(*id),
+                    };
+                    let harmless_teammate = !current_match.settings.teams.lethal_teammates
+                        && body_id.is_some_and(|id| are_teammates(connection, user_id, id));
+                    !body_effects.has(EffectKind::PassThrough)
+                        && !body_effects.has(EffectKind::SpawnProtection)
+                        && !harmless_teammate
+                })
+            }
+        } else {
+            obstacles.contains(other)
+        };
+        let Ok((_, _, _, effects, _)) = bots.get(snake) else {
+            continue;
+        };
+        let protected = effects.has(EffectKind::Invulnerable)
+            || (obstacles.get(other).is_err()
+                && (effects.has(EffectKind::PassThrough)
+                    || effects.has(EffectKind::SpawnProtection)));
+        if lethal && !protected {
+            snake_kill_writer.send(KillSnake { snake_id: snake });
+            send_for(connection, user_id, TransportMessage::KillSnake);
+        }
+    }
+}
+
+/// Kills bots whose head left the arena where its edge kills.
+pub fn check_bot_positions(
+    bots: Query<(Entity, &Bot)>,
+    heads: Query<&Transform, With<CellTag>>,
+    arena: Res<Arena>,
+    current_match: Res<CurrentMatch>,
+    connection_handler: Res<ConnectionState>,
+    mut snake_kill_writer: EventWriter<KillSnake>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    if !current_match.settings.mode.rules().edge_kills(&arena) {
+        return;
+    }
+    for (snake, bot) in bots.iter() {
+        let Ok(head) = heads.get(bot.head) else {
+            continue;
+        };
+        if !arena.contains(head.translation.truncate(), 0.0) {
+            snake_kill_writer.send(KillSnake { snake_id: snake });
+            send_for(connection, bot.user_id, TransportMessage::KillSnake);
+        }
+    }
+}
+
+/// Tells everyone where the host's bots are, like [`send_snake_send`] does for our own snake.
+///
+/// [`send_snake_send`]: crate::networking::send_snake_send
+pub fn send_bot_snakes(
+    transforms: Query<&Transform, Or<(With<SnakeTag>, With<CellTag>)>>,
+    moves: Query<(&Moves, &ActiveEffects, &SnakeSpeed)>,
+    moveid_direc: Query<(&Direction, &MoveId, &CellTag)>,
+    bots: Query<(Entity, &Bot)>,
+    snake_cells: Query<(&Parent, Entity), With<CellTag>>,
+    connection_handler: Res<ConnectionState>,
+    time: Res<Time>,
+    mut sync_timer: Local<Option<Timer>>,
+) {
+    let sync_timer = sync_timer
+        .get_or_insert_with(|| Timer::from_seconds(BOT_SYNC_SECONDS, TimerMode::Repeating));
+    sync_timer.tick(time.delta());
+    if !sync_timer.just_finished() {
+        return;
+    }
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    for (snake, bot) in bots.iter() {
+        if let Some(details) =
+            snake_details(snake, &transforms, &moves, &moveid_direc, &snake_cells)
+        {
+            send_for(
+                connection,
+                bot.user_id,
+                TransportMessage::SnakeUpdate(time.elapsed_seconds(), details),
+            );
+        }
+    }
+}
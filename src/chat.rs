@@ -0,0 +1,332 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    networking::{send, ConnectionHandler, ConnectionState, PeerMessage, TransportMessage},
+    GameStates,
+};
+
+/// Longest chat line, in characters.
+pub const MAX_CHAT_LENGTH: usize = 120;
+
+/// Lines anyone can send within [`RATE_WINDOW`] seconds.
+const RATE_LIMIT: usize = 5;
+const RATE_WINDOW: f32 = 10.0;
+
+/// Seconds between resends of a line not everyone has acknowledged yet.
+const RESEND_INTERVAL: f32 = 0.5;
+const MAX_RESENDS: u32 = 20;
+
+/// Line numbers remembered per sender. Resends of a line stop long before its sender gets
+/// this far past it, so anything older is a late copy.
+const SEEN_WINDOW: u32 = 64;
+
+/// Lines kept around for the panel.
+const LOG_LENGTH: usize = 50;
+const LINES_SHOWN: usize = 8;
+
+/// Seconds a line stays on screen during a match while the chat is closed.
+const LINE_LIFETIME: f32 = 8.0;
+
+struct ChatLine {
+    /// Sender of the line, or `None` for notices from the game itself.
+    author: Option<u32>,
+    text: String,
+    received_at: f32,
+}
+
+/// A line of ours that somebody hasn't acknowledged yet.
+struct PendingLine {
+    number: u32,
+    text: String,
+    /// Peers still to acknowledge it.
+    waiting: Vec<u32>,
+    last_sent: f32,
+    resends: u32,
+}
+
+#[derive(Resource, Default)]
+pub struct Chat {
+    log: VecDeque<ChatLine>,
+    /// Sender and number of the latest lines received from each peer, so resends show up once.
+    seen: Vec<(u32, u32)>,
+    /// When each peer's recent lines arrived, for the rate limit.
+    received: Vec<(u32, f32)>,
+    next_number: u32,
+    pending: Vec<PendingLine>,
+    sent: VecDeque<f32>,
+    /// What is being typed, while the input is open.
+    input: Option<String>,
+}
+
+impl Chat {
+    /// Whether keys are going into the chat input rather than the game.
+    pub fn is_typing(&self) -> bool {
+        self.input.is_some()
+    }
+
+    fn push(&mut self, author: Option<u32>, text: String, now: f32) {
+        self.log.push_back(ChatLine {
+            author,
+            text,
+            received_at: now,
+        });
+        while self.log.len() > LOG_LENGTH {
+            self.log.pop_front();
+        }
+    }
+
+    /// Sends a line to everyone and keeps it until they all have it.
+    fn send(&mut self, connection: &ConnectionHandler, text: String, now: f32) {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| now - sent > RATE_WINDOW)
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= RATE_LIMIT {
+            self.push(None, "Slow down, you are sending too fast".to_string(), now);
+            return;
+        }
+        self.sent.push_back(now);
+        self.push(connection.self_id, text.clone(), now);
+
+        let number = self.next_number;
+        self.next_number += 1;
+        let waiting: Vec<u32> = connection
+            .players
+            .iter()
+            .filter(|p| {
+                p.bot.is_none() && p.seat.is_none() && Some(p.user_id) != connection.self_id
+            })
+            .map(|p| p.user_id)
+            .collect();
+        send(connection, TransportMessage::Chat(number, text.clone()));
+        if !waiting.is_empty() {
+            self.pending.push(PendingLine {
+                number,
+                text,
+                waiting,
+                last_sent: now,
+                resends: 0,
+            });
+        }
+    }
+}
+
+/// Cuts a line down to what may be sent.
+fn clean_line(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_CHAT_LENGTH)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Enter opens the input and sends what was typed, escape closes it.
+pub fn chat_input(
+    mut chat: ResMut<Chat>,
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    connection_handler: Res<ConnectionState>,
+    time: Res<Time>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    let Some(mut input) = chat.input.take() else {
+        characters.clear();
+        if keys.just_pressed(KeyCode::Return) {
+            chat.input = Some(String::new());
+        }
+        return;
+    };
+    if keys.just_pressed(KeyCode::Escape) {
+        characters.clear();
+        return;
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        characters.clear();
+        let text = clean_line(&input);
+        if !text.is_empty() {
+            chat.send(connection, text, now);
+        }
+        return;
+    }
+    for event in characters.iter() {
+        if !event.char.is_control() && input.chars().count() < MAX_CHAT_LENGTH {
+            input.push(event.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        input.pop();
+    }
+    chat.input = Some(input);
+}
+
+/// Logs lines from the room, acknowledges them and clears acknowledged lines of ours.
+pub fn receive_chat(
+    mut chat: ResMut<Chat>,
+    mut peer_messages: EventReader<PeerMessage>,
+    connection_handler: Res<ConnectionState>,
+    time: Res<Time>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    for PeerMessage { user_id, message } in peer_messages.iter() {
+        match message {
+            TransportMessage::Chat(number, text) => {
+                send(connection, TransportMessage::ChatAck(*user_id, *number));
+                let already_seen = chat.seen.iter().any(|(id, seen)| {
+                    id == user_id && (seen == number || *seen >= number.saturating_add(SEEN_WINDOW))
+                });
+                if already_seen {
+                    continue;
+                }
+                chat.seen.push((*user_id, *number));
+                chat.seen.retain(|(id, seen)| {
+                    id != user_id || seen.saturating_add(SEEN_WINDOW) > *number
+                });
+                chat.received
+                    .retain(|(_, received)| now - received <= RATE_WINDOW);
+                // Someone flooding the room is ignored, even if their game doesn't hold back.
+                if chat.received.iter().filter(|(id, _)| id == user_id).count() >= RATE_LIMIT {
+                    continue;
+                }
+                chat.received.push((*user_id, now));
+                let text = clean_line(text);
+                if !text.is_empty() {
+                    chat.push(Some(*user_id), text, now);
+                }
+            }
+            TransportMessage::ChatAck(author, number) if Some(*author) == connection.self_id => {
+                for line in chat
+                    .pending
+                    .iter_mut()
+                    .filter(|line| line.number == *number)
+                {
+                    line.waiting.retain(|id| id != user_id);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Sends our lines again to whoever hasn't acknowledged them, and gives up on peers that left.
+pub fn resend_chat(
+    mut chat: ResMut<Chat>,
+    connection_handler: Res<ConnectionState>,
+    time: Res<Time>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    for line in chat.pending.iter_mut() {
+        line.waiting
+            .retain(|id| connection.players.iter().any(|p| p.user_id == *id));
+        if line.waiting.is_empty() || now - line.last_sent < RESEND_INTERVAL {
+            continue;
+        }
+        line.last_sent = now;
+        line.resends += 1;
+        send(
+            connection,
+            TransportMessage::Chat(line.number, line.text.clone()),
+        );
+    }
+    chat.pending
+        .retain(|line| !line.waiting.is_empty() && line.resends < MAX_RESENDS);
+    chat.seen
+        .retain(|(id, _)| connection.players.iter().any(|p| p.user_id == *id));
+}
+
+#[derive(Component)]
+pub struct ChatPanel;
+
+pub fn setup_chat(mut commands: Commands) {
+    commands.spawn((
+        ChatPanel,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::rgb(0.9, 0.9, 0.9),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(40.),
+            left: Val::Px(10.),
+            max_width: Val::Px(450.),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.4)),
+    ));
+}
+
+/// Always shows the chat in the lobby. During a match only the latest lines show, unless the
+/// input is open.
+pub fn display_chat(
+    chat: Res<Chat>,
+    mut panel: Query<(&mut Text, &mut Style), With<ChatPanel>>,
+    state: Res<State<GameStates>>,
+    connection_handler: Res<ConnectionState>,
+    time: Res<Time>,
+) {
+    let Ok((mut text, mut style)) = panel.get_single_mut() else {
+        return;
+    };
+    let in_lobby = *state.get() == GameStates::Lobby;
+    let in_match = *state.get() == GameStates::GamePlay;
+    let now = time.elapsed_seconds();
+    let name = |author: Option<u32>| match (author, connection_handler.as_ref()) {
+        (None, _) => None,
+        (Some(id), ConnectionState::Connected(connection)) => Some(
+            connection
+                .players
+                .iter()
+                .find(|p| p.user_id == id)
+                .map_or_else(|| format!("Player {id}"), |p| p.name()),
+        ),
+        (Some(id), ConnectionState::NotConnected) => Some(format!("Player {id}")),
+    };
+    let mut lines: Vec<String> = chat
+        .log
+        .iter()
+        .filter(|line| in_lobby || chat.input.is_some() || now - line.received_at < LINE_LIFETIME)
+        .map(|line| match name(line.author) {
+            Some(name) => format!("{name}: {}", line.text),
+            None => line.text.clone(),
+        })
+        .collect();
+    lines.drain(..lines.len().saturating_sub(LINES_SHOWN));
+    match &chat.input {
+        Some(input) => lines.push(format!("> {input}_")),
+        None if in_lobby => lines.push("Press Enter to chat".to_string()),
+        None => {}
+    }
+
+    let value = lines.join("\n");
+    let display = if (in_lobby || in_match) && !value.is_empty() {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    if style.display != display {
+        style.display = display;
+    }
+    if let Some(section) = text.sections.first_mut() {
+        if section.value != value {
+            section.value = value;
+        }
+    }
+}
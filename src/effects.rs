@@ -0,0 +1,300 @@
+use bevy::{prelude::*, render::texture::DEFAULT_IMAGE_HANDLE};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    food::Food,
+    networking::ConnectionState,
+    skins::{Skin, SkinTextures},
+    CellTag, Player, SnakeTag,
+};
+
+/// A temporary state a snake can be in, usually granted by eating a power-up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectKind {
+    /// Running into a body or an obstacle doesn't kill the snake.
+    Invulnerable,
+    /// The head passes through bodies, and other heads pass through this body.
+    PassThrough,
+    /// Nearby food drifts towards the head.
+    Magnet,
+    /// Growing food adds twice as many cells.
+    DoubleScore,
+    /// Moves faster than the snake's base speed.
+    Fast,
+    /// Moves slower than the snake's base speed.
+    Slow,
+    /// Just (re)spawned: bodies can't kill the snake, and other heads pass through its body.
+    SpawnProtection,
+}
+
+impl EffectKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EffectKind::Invulnerable => "Shield",
+            EffectKind::PassThrough => "Ghost",
+            EffectKind::Magnet => "Magnet",
+            EffectKind::DoubleScore => "x2",
+            EffectKind::Fast => "Fast",
+            EffectKind::Slow => "Slow",
+            EffectKind::SpawnProtection => "Protected",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            EffectKind::Invulnerable => Color::rgb(0.2, 0.9, 0.9),
+            EffectKind::PassThrough => Color::rgba(0.9, 0.95, 1.0, 0.6),
+            EffectKind::Magnet => Color::rgb(0.8, 0.1, 0.2),
+            EffectKind::DoubleScore => Color::rgb(0.3, 0.9, 0.3),
+            EffectKind::Fast => Color::rgb(1.0, 0.45, 0.1),
+            EffectKind::Slow => Color::rgb(0.3, 0.55, 1.0),
+            EffectKind::SpawnProtection => Color::rgb(0.95, 0.95, 0.95),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveEffect {
+    pub kind: EffectKind,
+    pub remaining: f32,
+}
+
+/// Effects currently applied to a snake, kept on the snake entity and replicated to peers.
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActiveEffects {
+    pub effects: Vec<ActiveEffect>,
+}
+
+impl ActiveEffects {
+    /// Starts `kind`, or extends it if it is already running.
+    pub fn add(&mut self, kind: EffectKind, seconds: f32) {
+        if let Some(effect) = self.effects.iter_mut().find(|e| e.kind == kind) {
+            effect.remaining = effect.remaining.max(seconds);
+        } else {
+            self.effects.push(ActiveEffect {
+                kind,
+                remaining: seconds,
+            });
+        }
+    }
+
+    pub fn has(&self, kind: EffectKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        let mut multiplier = 1.0;
+        if self.has(EffectKind::Fast) {
+            multiplier *= 1.6;
+        }
+        if self.has(EffectKind::Slow) {
+            multiplier *= 0.6;
+        }
+        multiplier
+    }
+}
+
+pub fn tick_active_effects(time: Res<Time>, mut effects: Query<&mut ActiveEffects>) {
+    for mut effects in effects.iter_mut() {
+        if effects.effects.is_empty() {
+            continue;
+        }
+        for effect in effects.effects.iter_mut() {
+            effect.remaining -= time.delta_seconds();
+        }
+        effects.effects.retain(|e| e.remaining > 0.);
+    }
+}
+
+/// Distance within which a magnet pulls food.
+const MAGNET_RADIUS: f32 = 250.0;
+const MAGNET_PULL_SPEED: f32 = 180.0;
+
+/// Pulls food towards every snake with a magnet. Effects are replicated, so every peer runs
+/// the same pull and the food ends up in the same place everywhere.
+pub fn magnet_pull_food(
+    time: Res<Time>,
+    snakes: Query<(&ActiveEffects, &Children), With<SnakeTag>>,
+    heads: Query<&Transform, (With<CellTag>, Without<Food>)>,
+    mut food: Query<&mut Transform, With<Food>>,
+) {
+    for (effects, children) in snakes.iter() {
+        if !effects.has(EffectKind::Magnet) {
+            continue;
+        }
+        let Some(head) = children.iter().find_map(|cell| heads.get(*cell).ok()) else {
+            continue;
+        };
+        for mut food in food.iter_mut() {
+            let offset = head.translation.truncate() - food.translation.truncate();
+            let distance = offset.length();
+            if distance > 1.0 && distance < MAGNET_RADIUS {
+                let step = (MAGNET_PULL_SPEED * time.delta_seconds()).min(distance);
+                let step = offset / distance * step;
+                food.translation += Vec3::new(step.x, step.y, 0.);
+            }
+        }
+    }
+}
+
+/// Paints every snake in its owner's skin and makes effects visible on the snakes wearing
+/// them, so every peer can tell who is protected.
+pub fn render_effects(
+    time: Res<Time>,
+    snakes: Query<(&SnakeTag, &ActiveEffects, &Children)>,
+    mut sprites: Query<(&mut Sprite, &mut Handle<Image>)>,
+    skin_textures: Option<Res<SkinTextures>>,
+    connection_handler: Res<ConnectionState>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    for (tag, effects, children) in snakes.iter() {
+        let user_id = match tag {
+            SnakeTag::SelfPlayerSnake => connection.self_id,
+            SnakeTag::OtherPlayerSnake(id) => Some(*id),
+        };
+        let Some(player) = connection
+            .players
+            .iter()
+            .find(|p| Some(p.user_id) == user_id)
+        else {
+            continue;
+        };
+        let skin = player.profile.skin;
+        let texture = match (skin, skin_textures.as_ref()) {
+            (Skin::Outline, Some(textures)) => textures.outline.clone(),
+            _ => DEFAULT_IMAGE_HANDLE.typed(),
+        };
+        for (index, cell) in children.iter().enumerate() {
+            let mut color = skin.cell_color(player.snake_color(), index, children.len());
+            if effects.has(EffectKind::Invulnerable) {
+                let pulse = (time.elapsed_seconds() * 8.).sin() * 0.5 + 0.5;
+                color = lerp_color(color, EffectKind::Invulnerable.color(), pulse * 0.6);
+            }
+            if effects.has(EffectKind::PassThrough) {
+                color.set_a(0.4);
+            }
+            if effects.has(EffectKind::SpawnProtection) {
+                let blink = (time.elapsed_seconds() * 10.).sin() > 0.0;
+                color.set_a(if blink { 0.25 } else { 0.6 });
+            }
+            if let Ok((mut sprite, mut image)) = sprites.get_mut(*cell) {
+                if sprite.color != color {
+                    sprite.color = color;
+                }
+                if *image != texture {
+                    *image = texture.clone();
+                }
+            }
+        }
+    }
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let (from, to) = (from.as_rgba_f32(), to.as_rgba_f32());
+    Color::rgba(
+        from[0] + (to[0] - from[0]) * t,
+        from[1] + (to[1] - from[1]) * t,
+        from[2] + (to[2] - from[2]) * t,
+        from[3],
+    )
+}
+
+#[derive(Component)]
+pub struct EffectsHud;
+
+#[derive(Component)]
+pub struct EffectIcon(EffectKind);
+
+#[derive(Component)]
+pub struct EffectTimerText(EffectKind);
+
+pub fn setup_effects_hud(mut commands: Commands) {
+    commands.spawn((
+        EffectsHud,
+        NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.),
+                position_type: PositionType::Absolute,
+                top: Val::Px(20.),
+                left: Val::Px(20.),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+pub fn display_effects(
+    snakes: Query<(&Player, &ActiveEffects)>,
+    hud: Query<Entity, With<EffectsHud>>,
+    icons: Query<(Entity, &EffectIcon)>,
+    mut timers: Query<(&mut Text, &EffectTimerText)>,
+    mut commands: Commands,
+) {
+    let Ok(hud) = hud.get_single() else {
+        return;
+    };
+    // Players sharing the keyboard see their effects in their own pane.
+    let effects = snakes
+        .iter()
+        .find(|(player, _)| player.keys.is_none())
+        .map(|(_, effects)| effects.effects.clone())
+        .unwrap_or_default();
+
+    for (icon, kind) in icons.iter() {
+        if !effects.iter().any(|e| e.kind == kind.0) {
+            commands.entity(icon).despawn_recursive();
+        }
+    }
+    for effect in effects.iter() {
+        let remaining = format!("{} {:.0}s", effect.kind.label(), effect.remaining.ceil());
+        if let Some((mut text, _)) = timers.iter_mut().find(|(_, t)| t.0 == effect.kind) {
+            if let Some(section) = text.sections.first_mut() {
+                if section.value != remaining {
+                    section.value = remaining;
+                }
+            }
+        } else if !icons.iter().any(|(_, icon)| icon.0 == effect.kind) {
+            commands.entity(hud).with_children(|parent| {
+                parent
+                    .spawn((
+                        EffectIcon(effect.kind),
+                        NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                align_items: AlignItems::Center,
+                                column_gap: Val::Px(8.),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(NodeBundle {
+                            background_color: effect.kind.color().into(),
+                            style: Style {
+                                width: Val::Px(20.),
+                                height: Val::Px(20.),
+                                ..default()
+                            },
+                            ..default()
+                        });
+                        parent.spawn((
+                            EffectTimerText(effect.kind),
+                            TextBundle::from_section(
+                                remaining,
+                                TextStyle {
+                                    font_size: 20.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                    ..default()
+                                },
+                            ),
+                        ));
+                    });
+            });
+        }
+    }
+}
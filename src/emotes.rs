@@ -0,0 +1,426 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chat::Chat,
+    networking::{send, ConnectionState, PeerMessage, TransportMessage},
+    CellTag, MainCamera, SnakeTag,
+};
+
+/// Seconds an emote bubble stays above a head.
+const BUBBLE_SECONDS: f32 = 3.0;
+
+/// Seconds a ping marker stays on the map.
+const PING_SECONDS: f32 = 4.0;
+
+/// Shortest gap between two emotes or pings from the same player, in seconds.
+const COOLDOWN: f32 = 0.75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Emote {
+    Laugh,
+    Gg,
+    Sorry,
+    WatchOut,
+}
+
+impl Emote {
+    /// In the order they sit around the picker, clockwise from the top, and on keys 1 to 4.
+    pub const ALL: [Emote; 4] = [Emote::Laugh, Emote::Gg, Emote::Sorry, Emote::WatchOut];
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            Emote::Laugh => "Haha!",
+            Emote::Gg => "GG",
+            Emote::Sorry => "Sorry!",
+            Emote::WatchOut => "Watch out!",
+        }
+    }
+}
+
+const EMOTE_KEYS: [KeyCode; 4] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+
+#[derive(Resource, Default)]
+pub struct EmoteState {
+    picker_open: bool,
+    /// When the picker's ping button was pressed; the next click or tap marks the map.
+    ping_armed_at: Option<f32>,
+    /// When each player last sent an emote or ping, ourselves included.
+    last_sent: Vec<(u32, f32)>,
+}
+
+impl EmoteState {
+    /// Whether `user_id` is allowed another one, noting it down if so.
+    fn allow(&mut self, user_id: u32, now: f32) -> bool {
+        if self
+            .last_sent
+            .iter()
+            .any(|(id, last)| *id == user_id && now - last < COOLDOWN)
+        {
+            return false;
+        }
+        self.last_sent.retain(|(id, _)| *id != user_id);
+        self.last_sent.push((user_id, now));
+        true
+    }
+}
+
+/// Toggles the emote picker, for touch screens.
+#[derive(Component)]
+pub struct EmoteToggleButton;
+
+#[derive(Component)]
+pub struct EmotePicker;
+
+#[derive(Component)]
+pub struct EmoteButton(Emote);
+
+#[derive(Component)]
+pub struct PingButton;
+
+#[derive(Component)]
+pub struct EmoteBubble {
+    user_id: u32,
+    expires_at: f32,
+}
+
+#[derive(Component)]
+pub struct PingMarker {
+    expires_at: f32,
+}
+
+fn button_style() -> Style {
+    Style {
+        position_type: PositionType::Absolute,
+        width: Val::Px(110.),
+        height: Val::Px(50.),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+fn spawn_label(parent: &mut ChildBuilder, text: &str) {
+    parent.spawn(TextBundle::from_section(
+        text,
+        TextStyle {
+            font_size: 22.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+            ..default()
+        },
+    ));
+}
+
+/// The emote button in the corner, and the picker it opens: one emote on each side, the ping
+/// in the middle.
+pub fn setup_emotes(mut commands: Commands, mut state: ResMut<EmoteState>) {
+    state.picker_open = false;
+    state.ping_armed_at = None;
+    commands
+        .spawn((
+            EmoteToggleButton,
+            ButtonBundle {
+                style: Style {
+                    right: Val::Px(20.),
+                    bottom: Val::Px(20.),
+                    ..button_style()
+                },
+                background_color: Color::rgba(0.15, 0.15, 0.15, 0.8).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| spawn_label(parent, "Emote"));
+
+    let size = 340.;
+    let offsets = [
+        (size / 2. - 55., 0.),
+        (size - 110., size / 2. - 25.),
+        (size / 2. - 55., size - 50.),
+        (0., size / 2. - 25.),
+    ];
+    commands
+        .spawn((
+            EmotePicker,
+            NodeBundle {
+                style: Style {
+                    display: Display::None,
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(50.),
+                    top: Val::Percent(50.),
+                    margin: UiRect {
+                        left: Val::Px(-size / 2.),
+                        top: Val::Px(-size / 2.),
+                        ..default()
+                    },
+                    width: Val::Px(size),
+                    height: Val::Px(size),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            for (emote, (left, top)) in Emote::ALL.into_iter().zip(offsets) {
+                parent
+                    .spawn((
+                        EmoteButton(emote),
+                        ButtonBundle {
+                            style: Style {
+                                left: Val::Px(left),
+                                top: Val::Px(top),
+                                ..button_style()
+                            },
+                            background_color: Color::rgba(0.15, 0.15, 0.15, 0.9).into(),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| spawn_label(parent, emote.text()));
+            }
+            parent
+                .spawn((
+                    PingButton,
+                    ButtonBundle {
+                        style: Style {
+                            left: Val::Px(size / 2. - 55.),
+                            top: Val::Px(size / 2. - 25.),
+                            ..button_style()
+                        },
+                        background_color: Color::rgba(0.3, 0.2, 0.1, 0.9).into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| spawn_label(parent, "Ping"));
+        });
+}
+
+/// Keys 1 to 4 emote and a right click pings. The picker does both by touch: a ping from it
+/// lands wherever the next click or tap goes.
+pub fn emote_input(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    toggle: Query<&Interaction, (Changed<Interaction>, With<EmoteToggleButton>)>,
+    emote_buttons: Query<(&Interaction, &EmoteButton), Changed<Interaction>>,
+    ping_button: Query<&Interaction, (Changed<Interaction>, With<PingButton>)>,
+    mut picker: Query<&mut Style, With<EmotePicker>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut state: ResMut<EmoteState>,
+    mut peer_messages: EventWriter<PeerMessage>,
+    chat: Res<Chat>,
+    connection_handler: Res<ConnectionState>,
+    time: Res<Time>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let Some(self_id) = connection.self_id else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    let pressed = |interaction: &Interaction| *interaction == Interaction::Pressed;
+
+    let mut emote = EMOTE_KEYS
+        .iter()
+        .zip(Emote::ALL)
+        .find(|(key, _)| !chat.is_typing() && keys.just_pressed(**key))
+        .map(|(_, emote)| emote);
+    if let Some((_, button)) = emote_buttons.iter().find(|(i, _)| pressed(i)) {
+        emote = Some(button.0);
+        state.picker_open = false;
+    }
+    if toggle.iter().any(pressed) {
+        state.picker_open = !state.picker_open;
+        state.ping_armed_at = None;
+    }
+    if ping_button.iter().any(pressed) {
+        state.picker_open = false;
+        state.ping_armed_at = Some(now);
+    }
+
+    // Not the click that pressed the ping button itself.
+    let armed = state.ping_armed_at.is_some_and(|armed_at| armed_at < now);
+    let cursor = window
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+    let target = if mouse.just_pressed(MouseButton::Right)
+        || (armed && mouse.just_pressed(MouseButton::Left))
+    {
+        cursor
+    } else if armed {
+        touches
+            .iter_just_pressed()
+            .next()
+            .map(|touch| touch.position())
+    } else {
+        None
+    };
+    let ping = target.and_then(|target| {
+        let (camera, transform) = camera.get_single().ok()?;
+        camera.viewport_to_world_2d(transform, target)
+    });
+    if ping.is_some() {
+        state.ping_armed_at = None;
+    }
+
+    for mut style in picker.iter_mut() {
+        let display = if state.picker_open {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+
+    // Ours are shown the same way as everyone else's.
+    if let Some(emote) = emote.filter(|_| state.allow(self_id, now)) {
+        send(connection, TransportMessage::Emote(emote));
+        peer_messages.send(PeerMessage {
+            user_id: self_id,
+            message: TransportMessage::Emote(emote),
+        });
+    }
+    if let Some(point) = ping.filter(|_| state.allow(self_id, now)) {
+        send(connection, TransportMessage::MapPing(point));
+        peer_messages.send(PeerMessage {
+            user_id: self_id,
+            message: TransportMessage::MapPing(point),
+        });
+    }
+}
+
+/// Shows the room's emotes above their senders' heads and marks pinged spots on the map.
+pub fn receive_emotes(
+    mut commands: Commands,
+    mut peer_messages: EventReader<PeerMessage>,
+    mut state: ResMut<EmoteState>,
+    bubbles: Query<(Entity, &EmoteBubble)>,
+    connection_handler: Res<ConnectionState>,
+    time: Res<Time>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    for PeerMessage { user_id, message } in peer_messages.iter() {
+        let color = connection
+            .players
+            .iter()
+            .find(|p| p.user_id == *user_id)
+            .map_or(Color::WHITE, |p| p.snake_color());
+        // Our own went through the cooldown when they were sent.
+        let allowed = |state: &mut EmoteState| {
+            Some(*user_id) == connection.self_id || state.allow(*user_id, now)
+        };
+        match message {
+            TransportMessage::Emote(emote) if allowed(&mut state) => {
+                for (entity, bubble) in bubbles.iter() {
+                    if bubble.user_id == *user_id {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+                commands.spawn((
+                    EmoteBubble {
+                        user_id: *user_id,
+                        expires_at: now + BUBBLE_SECONDS,
+                    },
+                    Text2dBundle {
+                        text: Text::from_section(
+                            emote.text(),
+                            TextStyle {
+                                font_size: 26.0,
+                                color,
+                                ..default()
+                            },
+                        ),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                ));
+            }
+            TransportMessage::MapPing(point) if allowed(&mut state) => {
+                commands
+                    .spawn((
+                        PingMarker {
+                            expires_at: now + PING_SECONDS,
+                        },
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color,
+                                custom_size: Some(Vec2::splat(36.)),
+                                ..default()
+                            },
+                            transform: Transform::from_translation(point.extend(5.0))
+                                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(Text2dBundle {
+                            text: Text::from_section(
+                                "!",
+                                TextStyle {
+                                    font_size: 30.0,
+                                    color: Color::BLACK,
+                                    ..default()
+                                },
+                            ),
+                            transform: Transform::from_translation(Vec3::Z)
+                                .with_rotation(Quat::from_rotation_z(-std::f32::consts::FRAC_PI_4)),
+                            ..default()
+                        });
+                    });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Keeps bubbles over the heads they belong to and fades out pings.
+pub fn update_emotes(
+    mut commands: Commands,
+    mut bubbles: Query<(Entity, &EmoteBubble, &mut Transform, &mut Visibility)>,
+    mut markers: Query<(Entity, &PingMarker, &mut Sprite)>,
+    snakes: Query<(&SnakeTag, &Children)>,
+    heads: Query<&GlobalTransform, With<CellTag>>,
+    connection_handler: Res<ConnectionState>,
+    time: Res<Time>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    for (entity, bubble, mut transform, mut visibility) in bubbles.iter_mut() {
+        if now >= bubble.expires_at {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let head = snakes
+            .iter()
+            .find(|(tag, _)| match tag {
+                SnakeTag::SelfPlayerSnake => connection.self_id == Some(bubble.user_id),
+                SnakeTag::OtherPlayerSnake(id) => *id == bubble.user_id,
+            })
+            .and_then(|(_, cells)| cells.iter().find_map(|cell| heads.get(*cell).ok()));
+        // Nobody to say it, as when the sender is dead or only watching.
+        let Some(head) = head else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        transform.translation = (head.translation().truncate() + Vec2::new(0.0, 60.0)).extend(11.0);
+        *visibility = Visibility::Visible;
+    }
+    for (entity, marker, mut sprite) in markers.iter_mut() {
+        let left = marker.expires_at - now;
+        if left <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        sprite
+            .color
+            .set_a((left / PING_SECONDS).min(1.0) * 0.5 + 0.3);
+    }
+}
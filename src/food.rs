@@ -1,56 +1,201 @@
 use bevy::{prelude::*, window::PrimaryWindow};
 use bevy_rapier2d::prelude::{Collider, CollisionEvent, Sensor};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    networking::{ConnectionState, SendMessage, TransportMessage},
-    snek::KillSnake,
-    CellTag, GameConfig, HeadSensor, Host, MoveId, SnakeCell, SnakeTag, Tail,
+    arena::Arena,
+    effects::{ActiveEffects, EffectKind},
+    game_mode::{BodyCollisions, CurrentMatch},
+    networking::{send_for, ConnectionState, SendMessage, TransportMessage},
+    obstacles::Obstacle,
+    snek::{shed_tail_cells, KillSnake},
+    teams::are_teammates,
+    CellTag, GameConfig, HeadSensor, Host, MainCamera, MoveId, Moves, SnakeCell, SnakeTag, Tail,
 };
 
 #[derive(Component, Debug)]
 pub struct Food(pub u32);
 
+/// What happens to a snake that eats a food item.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FoodKind {
+    /// Adds one cell.
+    Normal,
+    /// Adds five cells.
+    Golden,
+    /// Removes a few cells, never going below the starting length.
+    Shrink,
+    /// Temporarily speeds the snake up.
+    Speed,
+    /// Temporarily slows the snake down.
+    Slow,
+    /// Temporarily lets the snake pass through bodies.
+    Ghost,
+    /// Temporarily protects the snake from running into bodies.
+    Shield,
+    /// Temporarily pulls nearby food towards the snake.
+    Magnet,
+    /// Temporarily doubles the growth from food.
+    DoubleScore,
+}
+
+impl FoodKind {
+    pub fn color(&self) -> Color {
+        match self {
+            FoodKind::Normal => Color::rgb(0.85, 0.25, 0.75),
+            FoodKind::Golden => Color::rgb(1.0, 0.84, 0.0),
+            FoodKind::Shrink => Color::rgb(0.45, 0.45, 0.5),
+            _ => self
+                .effect()
+                .map_or(Color::WHITE, |(effect, _)| effect.color()),
+        }
+    }
+
+    /// Size of the food relative to a snake cell.
+    pub fn scale(&self) -> f32 {
+        match self {
+            FoodKind::Golden => 1.4,
+            _ => 1.0,
+        }
+    }
+
+    /// The timed effect eating this food grants, and for how many seconds.
+    pub fn effect(&self) -> Option<(EffectKind, f32)> {
+        match self {
+            FoodKind::Normal | FoodKind::Golden | FoodKind::Shrink => None,
+            FoodKind::Speed => Some((EffectKind::Fast, 5.0)),
+            FoodKind::Slow => Some((EffectKind::Slow, 5.0)),
+            FoodKind::Ghost => Some((EffectKind::PassThrough, 5.0)),
+            FoodKind::Shield => Some((EffectKind::Invulnerable, 6.0)),
+            FoodKind::Magnet => Some((EffectKind::Magnet, 8.0)),
+            FoodKind::DoubleScore => Some((EffectKind::DoubleScore, 10.0)),
+        }
+    }
+}
+
+/// Relative chance of each [`FoodKind`] being picked when the host spawns food.
+#[derive(Debug, Clone)]
+pub struct FoodWeights(pub Vec<(FoodKind, u32)>);
+
+impl Default for FoodWeights {
+    fn default() -> Self {
+        Self(vec![
+            (FoodKind::Normal, 70),
+            (FoodKind::Golden, 5),
+            (FoodKind::Shrink, 8),
+            (FoodKind::Speed, 7),
+            (FoodKind::Slow, 5),
+            (FoodKind::Ghost, 5),
+            (FoodKind::Shield, 3),
+            (FoodKind::Magnet, 3),
+            (FoodKind::DoubleScore, 3),
+        ])
+    }
+}
+
+impl FoodWeights {
+    pub fn pick(&self) -> FoodKind {
+        let total = self.0.iter().map(|(_, weight)| weight).sum::<u32>();
+        if total == 0 {
+            return FoodKind::Normal;
+        }
+        let mut roll = rand::random::<u32>() % total;
+        for (kind, weight) in self.0.iter() {
+            if roll < *weight {
+                return *kind;
+            }
+            roll -= weight;
+        }
+        FoodKind::Normal
+    }
+}
+
+/// How many cells a [`FoodKind::Shrink`] takes away.
+const SHRINK_CELLS: usize = 3;
+
+/// How far from the arena edge food is kept.
+const FOOD_SPAWN_MARGIN: f32 = 100.0;
+
+/// How many food items the host keeps on the field at the same time.
+#[derive(Debug, Clone, Copy)]
+pub enum FoodDensity {
+    /// Always keep this many food items around.
+    Fixed(u32),
+    /// Keep `per_player` items around for every player in the room, never fewer than `min`.
+    PerPlayer { per_player: u32, min: u32 },
+    /// Keep one item around for every `area_per_food` square units of the spawn area.
+    PerArea { area_per_food: f32 },
+}
+
+impl FoodDensity {
+    /// Number of food items that should exist for the given number of players.
+    pub fn target_count(&self, players: usize, arena: &Arena) -> usize {
+        match *self {
+            FoodDensity::Fixed(count) => count as usize,
+            FoodDensity::PerPlayer { per_player, min } => {
+                (per_player as usize * players).max(min as usize)
+            }
+            FoodDensity::PerArea { area_per_food } => {
+                (arena.area() / area_per_food.max(1.0)).round() as usize
+            }
+        }
+    }
+}
+
 pub fn spawn_food_system(
     mut commands: Commands,
     food_query: Query<&Food>,
     config: Res<GameConfig>,
+    arena: Res<Arena>,
     host: Query<&Host>,
     connection_handler: Res<ConnectionState>,
 ) {
     if host.is_empty() {
         return;
     }
-    if food_query.is_empty() {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let target = config
+        .food_density
+        .target_count(connection.playing().count(), &arena);
+    for _ in food_query.iter().count()..target {
         let (pos_x, pos_y) = {
-            let rad = rand::random::<f32>() * 900.0;
-            let angle = rand::random::<f32>() * std::f32::consts::PI * 2.0;
-            let (sin, cos) = angle.sin_cos();
-            (rad * sin, rad * cos)
+            let position = arena.random_point(&mut rand::thread_rng(), FOOD_SPAWN_MARGIN);
+            (position.x, position.y)
         };
-        if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
-            let food_id = rand::random();
-            if let Err(err) =
-                connection
-                    .sender
-                    .send(SendMessage::TransportMessage(TransportMessage::SpawnFood(
-                        food_id,
-                        Vec2 { x: pos_x, y: pos_y },
-                    )))
-            {
-                warn!("{err:?}")
-            }
-            commands.spawn(spawn_food(food_id, config.cell_size, pos_x, pos_y));
+        let food_id = rand::random();
+        let kind = config.food_weights.pick();
+        if let Err(err) =
+            connection
+                .sender
+                .send(SendMessage::TransportMessage(TransportMessage::SpawnFood(
+                    food_id,
+                    Vec2 { x: pos_x, y: pos_y },
+                    kind,
+                )))
+        {
+            warn!("{err:?}")
         }
+        commands.spawn(spawn_food(food_id, kind, config.cell_size, pos_x, pos_y));
     }
 }
 
-pub fn spawn_food(id: u32, cell_size: (f32, f32), pos_x: f32, pos_y: f32) -> impl Bundle {
+pub fn spawn_food(
+    id: u32,
+    kind: FoodKind,
+    cell_size: (f32, f32),
+    pos_x: f32,
+    pos_y: f32,
+) -> impl Bundle {
+    let size = (cell_size.0 * kind.scale(), cell_size.1 * kind.scale());
     (
         Food(id),
+        kind,
         SpriteBundle {
             sprite: Sprite {
-                color: Color::rgb(0.85, 0.25, 0.75),
-                custom_size: Some(Vec2::new(cell_size.0, cell_size.1)),
+                color: kind.color(),
+                custom_size: Some(Vec2::new(size.0, size.1)),
                 ..default()
             },
             transform: Transform::from_translation(Vec3 {
@@ -60,135 +205,292 @@ pub fn spawn_food(id: u32, cell_size: (f32, f32), pos_x: f32, pos_y: f32) -> imp
             }),
             ..Default::default()
         },
-        Collider::cuboid(cell_size.0 / 2.0, cell_size.1 / 2.0),
+        Collider::cuboid(size.0 / 2.0, size.1 / 2.0),
         Sensor,
     )
 }
 
+/// Eating and dying for the snakes of the players at this machine.
 pub fn handle_food_collision(
     mut collision_events: EventReader<CollisionEvent>,
     head_sensor: Query<(Entity, &HeadSensor, &Parent)>,
-    food: Query<(Entity, &Food)>,
-    body_cell: Query<Entity, With<CellTag>>,
-    // mut snek: Query<&mut Spawner>,
-    snek: Query<(Entity, &SnakeTag)>,
+    food: Query<(Entity, &Food, &FoodKind)>,
+    body_cell: Query<&Parent, With<CellTag>>,
+    obstacles: Query<Entity, With<Obstacle>>,
+    mut snek: Query<(Entity, &SnakeTag, &Children, &mut ActiveEffects)>,
     mut commands: Commands,
     connection_handler: Res<ConnectionState>,
-    snek_main: Query<(Entity, &SnakeTag)>,
     mut snake_kill_writer: EventWriter<KillSnake>,
     config: Res<GameConfig>,
     tail: Query<(&Parent, &Transform, &crate::Direction, &MoveId, Entity), With<Tail>>,
+    cell_move_ids: Query<&MoveId, With<CellTag>>,
+    mut moves: Query<&mut Moves>,
+    current_match: Res<CurrentMatch>,
 ) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
     for collision_event in collision_events.iter() {
         if let CollisionEvent::Started(object, collider, _flags) = collision_event {
-            // let heads = head_sensor.iter().map(|e|e.0).collect::<Vec<_>>();
-            // let foods = food.iter().map(|e|e.0).collect::<Vec<_>>();
-
             let food = food.get(*collider).or(food.get(*object));
             let head = head_sensor.get(*object).or(head_sensor.get(*collider));
             let cell = body_cell.get(*collider);
-            // info!("Collision food: {:?} head: {:?} cell {:?} object:{object:?}, collider: {collider:?} flags:{_flags:?}\nheads:{heads:?}\nfoods:{foods:?}", food, head, cell);
-            if let (Ok(_head), Ok(food)) = (head, food) {
+            // The sensor sits on the head, which sits on the snake.
+            let Ok(snake) = head.and_then(|(_, _, head)| body_cell.get(head.get())) else {
+                continue;
+            };
+            let snake = snake.get();
+            let Some(user_id) = snek
+                .get(snake)
+                .ok()
+                .and_then(|snek| connection.snake_owner(snek.1))
+            else {
+                continue;
+            };
+            if let Ok(food) = food {
                 commands.entity(food.0).despawn_recursive();
-                let collider_size = (config.cell_size.0 / 2.0, config.cell_size.1 / 2.0);
-                let snek = snek.iter().find(|p| p.1 == &SnakeTag::SelfPlayerSnake);
-                if let Some(snek) = snek {
-                    let tail = tail.iter().find(|tail| tail.0.get() == snek.0);
-                    if let Some(tail) = tail {
-                        if let ConnectionState::Connected(connection) = connection_handler.as_ref()
-                        {
-                            if let Some(player_id) = connection.self_id {
-                                if let Some(player) =
-                                    connection.players.iter().find(|p| p.user_id == player_id)
-                                {
-                                    let tail_position = tail.1.translation
-                                        - Vec3 {
-                                            x: tail.2 .0.x * config.cell_size.0,
-                                            y: tail.2 .0.y * config.cell_size.1,
-                                            z: 0.0,
-                                        };
-                                    let new_cell = SnakeCell {
-                                        cell_tag: CellTag(rand::random()),
-
-                                        collider: Collider::cuboid(
-                                            collider_size.0,
-                                            collider_size.1,
-                                        ),
-                                        sensor: Sensor,
-                                        direction: crate::Direction(tail.2 .0),
-                                        move_id: MoveId(tail.3 .0),
-                                        sprite: SpriteBundle {
-                                            sprite: Sprite {
-                                                color: player.color,
-                                                custom_size: Some(Vec2::new(
-                                                    config.cell_size.0,
-                                                    config.cell_size.1,
-                                                )),
-                                                ..default()
-                                            },
-                                            transform: Transform::from_translation(tail_position),
-                                            ..default()
-                                        },
-                                    };
-                                    let new_tail = commands.spawn(new_cell).insert(Tail).id();
-                                    commands.entity(snek.0).push_children(&[new_tail]);
-                                    commands.entity(tail.4).remove::<Tail>();
-                                }
+                let Ok(mut snek) = snek.get_mut(snake) else {
+                    continue;
+                };
+                let Some(player) = connection.players.iter().find(|p| p.user_id == user_id) else {
+                    continue;
+                };
+                let Ok(mut snek_moves) = moves.get_mut(snek.0) else {
+                    continue;
+                };
+                let effects_changed = eat_food(
+                    &mut commands,
+                    &config,
+                    *food.2,
+                    player.snake_color(),
+                    (snek.0, snek.2, &mut snek.3, &mut snek_moves),
+                    &tail,
+                    &cell_move_ids,
+                );
+                if effects_changed {
+                    send_for(
+                        connection,
+                        user_id,
+                        TransportMessage::EffectsChanged(snek.3.clone()),
+                    );
+                }
 
-                                if let Err(err) =
-                                    connection.sender.send(SendMessage::TransportMessage(
-                                        TransportMessage::DespawnFood(food.1 .0),
-                                    ))
-                                {
-                                    warn!("{err:?}")
-                                }
-                            }
-                        }
-                    }
+                if let Err(err) = connection.sender.send(SendMessage::TransportMessage(
+                    TransportMessage::DespawnFood(food.1 .0),
+                )) {
+                    warn!("{err:?}")
                 }
-            } else if let (Ok(_head), Ok(_cell)) = (head, cell) {
-                if let Some(snek) = snek_main.iter().find(|s| s.1 == &SnakeTag::SelfPlayerSnake) {
-                    snake_kill_writer.send(KillSnake { snake_id: snek.0 });
-                    if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
-                        if let Err(err) = connection
-                            .sender
-                            .send(SendMessage::TransportMessage(TransportMessage::KillSnake))
-                        {
-                            warn!("{err:?}")
-                        }
+            } else {
+                let lethal = if let Ok(cell_parent) = cell {
+                    let body_snek = snek.get(cell_parent.get()).ok();
+                    // Bodies of ghosted or freshly spawned snakes can't be run into.
+                    let ghosted = body_snek.is_some_and(|body_snek| {
+                        body_snek.3.has(EffectKind::PassThrough)
+                            || body_snek.3.has(EffectKind::SpawnProtection)
+                    });
+                    let harmless_teammate = !current_match.settings.teams.lethal_teammates
+                        && body_snek.is_some_and(|body_snek| {
+                            connection
+                                .snake_owner(body_snek.1)
+                                .is_some_and(|id| are_teammates(connection, user_id, id))
+                        });
+                    current_match.settings.bodies == BodyCollisions::Lethal
+                        && !ghosted
+                        && !harmless_teammate
+                } else {
+                    obstacles.get(*collider).or(obstacles.get(*object)).is_ok()
+                };
+                if !lethal {
+                    continue;
+                }
+                if let Ok(snek) = snek.get(snake) {
+                    if snek.3.has(EffectKind::Invulnerable)
+                        || (cell.is_ok()
+                            && (snek.3.has(EffectKind::PassThrough)
+                                || snek.3.has(EffectKind::SpawnProtection)))
+                    {
+                        continue;
                     }
+                    snake_kill_writer.send(KillSnake { snake_id: snek.0 });
+                    send_for(connection, user_id, TransportMessage::KillSnake);
                 }
             }
         }
     }
 }
 
-// pub fn
+/// Grows, shrinks or powers up a snake that ate a `kind` of food. Returns whether its effects
+/// changed, which the peers only hear about when they are told.
+pub fn eat_food(
+    commands: &mut Commands,
+    config: &GameConfig,
+    kind: FoodKind,
+    color: Color,
+    (snake, children, effects, moves): (Entity, &Children, &mut ActiveEffects, &mut Moves),
+    tail: &Query<(&Parent, &Transform, &crate::Direction, &MoveId, Entity), With<Tail>>,
+    cell_move_ids: &Query<&MoveId, With<CellTag>>,
+) -> bool {
+    match kind {
+        FoodKind::Normal | FoodKind::Golden => {
+            let Some(tail) = tail.iter().find(|tail| tail.0.get() == snake) else {
+                return false;
+            };
+            let mut count = if kind == FoodKind::Golden { 5 } else { 1 };
+            if effects.has(EffectKind::DoubleScore) {
+                count *= 2;
+            }
+            grow_snake(
+                commands,
+                config,
+                color,
+                snake,
+                (tail.1, tail.2, tail.3, tail.4),
+                count,
+            );
+            false
+        }
+        FoodKind::Shrink => {
+            shed_tail_cells(commands, children, cell_move_ids, moves, SHRINK_CELLS);
+            false
+        }
+        kind => {
+            let Some((effect, seconds)) = kind.effect() else {
+                return false;
+            };
+            effects.add(effect, seconds);
+            true
+        }
+    }
+}
 
+/// Adds `count` cells to `snake` behind its `tail`.
+pub fn grow_snake(
+    commands: &mut Commands,
+    config: &GameConfig,
+    color: Color,
+    snake: Entity,
+    tail: (&Transform, &crate::Direction, &MoveId, Entity),
+    count: usize,
+) {
+    let direction = tail.1 .0;
+    let mut tail_entity = tail.3;
+    let mut tail_position = tail.0.translation;
+    for _ in 0..count {
+        tail_position -= Vec3 {
+            x: direction.x * config.cell_size.0,
+            y: direction.y * config.cell_size.1,
+            z: 0.0,
+        };
+        let new_tail = commands
+            .spawn(new_snake_cell(
+                config,
+                color,
+                Transform::from_translation(tail_position),
+                direction,
+                tail.2 .0,
+            ))
+            .insert(Tail)
+            .id();
+        commands.entity(snake).push_children(&[new_tail]);
+        commands.entity(tail_entity).remove::<Tail>();
+        tail_entity = new_tail;
+    }
+}
+
+/// A body cell for a snake, to be parented to the snake entity.
+pub fn new_snake_cell(
+    config: &GameConfig,
+    color: Color,
+    transform: Transform,
+    direction: Vec2,
+    move_id: u32,
+) -> SnakeCell {
+    let collider_size = (config.cell_size.0 / 2.0, config.cell_size.1 / 2.0);
+    SnakeCell {
+        cell_tag: CellTag(rand::random()),
+        collider: Collider::cuboid(collider_size.0, collider_size.1),
+        sensor: Sensor,
+        direction: crate::Direction(direction),
+        move_id: MoveId(move_id),
+        sprite: SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::new(config.cell_size.0, config.cell_size.1)),
+                ..default()
+            },
+            transform,
+            ..default()
+        },
+    }
+}
+
+/// Arrow at the edge of the screen pointing at an off-screen food item.
+///
+/// The index is the rank of the food it points at, sorted by distance from the camera.
 #[derive(Component)]
-pub struct FoodPointer;
+pub struct FoodPointer(pub usize);
 
 pub fn sync_food_pointer(
     food: Query<Entity, With<Food>>,
-    pointer: Query<Entity, With<FoodPointer>>,
+    pointers: Query<(Entity, &FoodPointer)>,
     mut transform: Query<&mut Transform>,
     global_transform: Query<&GlobalTransform>,
-    camera: Query<(Entity, &Camera)>,
+    camera: Query<(Entity, &Camera), With<MainCamera>>,
     q_window: Query<&Window, With<PrimaryWindow>>,
     mut q_visibility: Query<&mut Visibility>,
 ) {
-    let (Ok(food), Ok(pointer), Ok((camera_entity, camera)), Ok(window)) = (
-        food.get_single(),
-        pointer.get_single(),
-        camera.get_single(),
-        q_window.get_single(),
-    ) else {
+    let (Ok((camera_entity, camera)), Ok(window)) = (camera.get_single(), q_window.get_single())
+    else {
         return;
     };
     let Ok(camera_transform) = global_transform.get(camera_entity) else {
         return;
     };
-    let rect = (window.width(), window.height());
+    let camera_pos = camera_transform.translation();
+    let mut nearest_food = food
+        .iter()
+        .filter_map(|food| transform.get(food).ok().map(|t| t.translation))
+        .collect::<Vec<_>>();
+    nearest_food.sort_by(|a, b| {
+        a.truncate()
+            .distance_squared(camera_pos.truncate())
+            .total_cmp(&b.truncate().distance_squared(camera_pos.truncate()))
+    });
+
+    for (pointer, rank) in pointers.iter() {
+        let placement = nearest_food.get(rank.0).and_then(|food_pos| {
+            food_pointer_placement(camera, camera_transform, window, *food_pos)
+        });
+        let visible = if let Some((translation, rotation)) = placement {
+            if let Ok(mut pointer_transform) = transform.get_mut(pointer) {
+                pointer_transform.translation = translation;
+                pointer_transform.rotation = rotation;
+            }
+            true
+        } else {
+            false
+        };
+        if let Ok(mut visibility) = q_visibility.get_mut(pointer) {
+            *visibility = if visible {
+                Visibility::Visible
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+}
+
+/// Where to put a pointer on the screen edge for food at `food_pos`, or `None` if it's on screen.
+fn food_pointer_placement(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    window: &Window,
+    food_pos: Vec3,
+) -> Option<(Vec3, Quat)> {
+    // The main camera only gets part of the window while it is split.
+    let rect = camera
+        .logical_viewport_size()
+        .map_or((window.width(), window.height()), |size| (size.x, size.y));
     let (Some(top_left), Some(_top_right), Some(bottom_left), Some(bottom_right)) = (
         camera.viewport_to_world_2d(camera_transform, Vec2 { x: 0., y: 0. }),
         camera.viewport_to_world_2d(camera_transform, Vec2 { x: rect.0, y: 0. }),
@@ -201,15 +503,12 @@ pub fn sync_food_pointer(
             },
         ),
     ) else {
-        return;
-    };
-    let Ok(food_pos) = transform.get(food) else {
-        return;
+        return None;
     };
 
     let ray = Ray {
         origin: camera_transform.translation(),
-        direction: food_pos.translation - camera_transform.translation(),
+        direction: food_pos - camera_transform.translation(),
     };
     let normal = [
         (top_left, bottom_left - top_left),
@@ -230,30 +529,21 @@ pub fn sync_food_pointer(
             }
         }
     }
-    if let Some(dist) = dist {
-        let pt = ray.get_point(dist);
-        let angle = Vec2 { x: -1.0, y: 0.0 }.angle_between(ray.direction.truncate().normalize());
-        let visible = camera_transform
-            .translation()
-            .truncate()
-            .distance_squared(food_pos.translation.truncate())
-            > pt.truncate()
-                .distance_squared(camera_transform.translation().truncate());
-
-        let Ok(mut pointer_transform) = transform.get_mut(pointer) else {
-            return;
-        };
-        let pt = Vec3::new(pt.x, pt.y, 2.0);
-        let offset = -20.0 * ray.direction.truncate().normalize();
-        let offset = Vec3::new(offset.x, offset.y, 2.0);
-        pointer_transform.translation = pt + offset;
-        pointer_transform.rotation = Quat::from_rotation_z(angle);
-        *q_visibility.get_mut(pointer).unwrap() = if visible {
-            Visibility::Visible
-        } else {
-            Visibility::Hidden
-        };
-
-        // pointer_transform.look_to(food_pos, Vec3::Y);
+    let dist = dist?;
+    let pt = ray.get_point(dist);
+    let angle = Vec2 { x: -1.0, y: 0.0 }.angle_between(ray.direction.truncate().normalize());
+    let visible = camera_transform
+        .translation()
+        .truncate()
+        .distance_squared(food_pos.truncate())
+        > pt.truncate()
+            .distance_squared(camera_transform.translation().truncate());
+    if !visible {
+        return None;
     }
+
+    let pt = Vec3::new(pt.x, pt.y, 2.0);
+    let offset = -20.0 * ray.direction.truncate().normalize();
+    let offset = Vec3::new(offset.x, offset.y, 2.0);
+    Some((pt + offset, Quat::from_rotation_z(angle)))
 }
@@ -0,0 +1,779 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::{Arena, ArenaShape, EdgeBehaviour},
+    food::{FoodDensity, FoodKind, FoodWeights},
+    networking::{PointInTime, SnakeSyncTimer},
+    GameConfig,
+};
+
+/// Rules a match is played with, picked by the host in the lobby.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Eat, grow and respawn as often as you like.
+    #[default]
+    FreePlay,
+    /// The arena closes in, nobody respawns, and the last snake alive wins.
+    BattleRoyale,
+    /// Points tick up while your head is the only one on the hill.
+    KingOfTheHill,
+    /// Grab the flag, drag it behind you and bring it to your base.
+    CaptureTheFlag,
+}
+
+impl GameMode {
+    const ALL: [GameMode; 4] = [
+        GameMode::FreePlay,
+        GameMode::BattleRoyale,
+        GameMode::KingOfTheHill,
+        GameMode::CaptureTheFlag,
+    ];
+
+    /// The rules behind this mode. Systems ask these instead of matching on the mode, so a
+    /// new mode only needs a variant here and a [`ModeRules`] implementation.
+    pub fn rules(&self) -> &'static dyn ModeRules {
+        match self {
+            GameMode::FreePlay => &FreePlayRules,
+            GameMode::BattleRoyale => &BattleRoyaleRules,
+            GameMode::KingOfTheHill => &KingOfTheHillRules,
+            GameMode::CaptureTheFlag => &CaptureTheFlagRules,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        self.rules().label()
+    }
+
+    /// The mode after this one, for cycling through them in the lobby.
+    pub fn next(&self) -> Self {
+        let index = GameMode::ALL
+            .iter()
+            .position(|mode| mode == self)
+            .unwrap_or(0);
+        GameMode::ALL[(index + 1) % GameMode::ALL.len()]
+    }
+}
+
+/// What a mode places in the arena to play for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeObjective {
+    Hill,
+    Flag,
+}
+
+/// Scoring, respawn and win rules of a [`GameMode`].
+///
+/// The defaults are the classic rules: score by length, respawn at will, die at a solid
+/// arena edge, and play on until the round limit ends the match.
+pub trait ModeRules: Sync {
+    fn label(&self) -> &'static str;
+
+    /// Score of a snake `cells` long, or `None` when the mode hands out points itself.
+    fn length_score(&self, cells: usize) -> Option<u32> {
+        Some(cells.saturating_sub(3) as u32)
+    }
+
+    fn allows_respawn(&self) -> bool {
+        true
+    }
+
+    /// Whether the arena closes in as the match goes on.
+    fn shrinks_arena(&self) -> bool {
+        false
+    }
+
+    /// Whether a head leaving `arena` dies, rather than coming back in on the other side.
+    fn edge_kills(&self, arena: &Arena) -> bool {
+        !arena.wraps()
+    }
+
+    fn objective(&self) -> Option<ModeObjective> {
+        None
+    }
+
+    /// How the match ends given the players whose snakes are still alive, or `None` while
+    /// it goes on. `team` is a player's team, `None` when teams are off.
+    fn outcome(&self, _alive: &[u32], _team: &dyn Fn(u32) -> Option<u8>) -> Option<MatchOutcome> {
+        None
+    }
+
+    /// How likely each kind of food is to show up.
+    fn food_weights(&self) -> FoodWeights {
+        FoodWeights::default()
+    }
+}
+
+struct FreePlayRules;
+
+impl ModeRules for FreePlayRules {
+    fn label(&self) -> &'static str {
+        "Free play"
+    }
+}
+
+struct BattleRoyaleRules;
+
+impl ModeRules for BattleRoyaleRules {
+    fn label(&self) -> &'static str {
+        "Battle royale"
+    }
+
+    fn allows_respawn(&self) -> bool {
+        false
+    }
+
+    fn shrinks_arena(&self) -> bool {
+        true
+    }
+
+    /// The closing zone has to eliminate someone, so no edge lets a head through.
+    fn edge_kills(&self, _arena: &Arena) -> bool {
+        true
+    }
+
+    /// The last snake, or the last team, standing wins.
+    fn outcome(&self, alive: &[u32], team: &dyn Fn(u32) -> Option<u8>) -> Option<MatchOutcome> {
+        match alive {
+            [] => Some(MatchOutcome::Draw),
+            [first, rest @ ..] => match team(*first) {
+                Some(first_team) if rest.iter().all(|id| team(*id) == Some(first_team)) => {
+                    Some(MatchOutcome::TeamWinner(first_team))
+                }
+                None if rest.is_empty() => Some(MatchOutcome::Winner(*first)),
+                _ => None,
+            },
+        }
+    }
+
+    /// Nobody comes back, so more of what keeps a snake alive.
+    fn food_weights(&self) -> FoodWeights {
+        FoodWeights(vec![
+            (FoodKind::Normal, 60),
+            (FoodKind::Golden, 5),
+            (FoodKind::Shrink, 5),
+            (FoodKind::Speed, 8),
+            (FoodKind::Slow, 4),
+            (FoodKind::Ghost, 8),
+            (FoodKind::Shield, 6),
+            (FoodKind::Magnet, 2),
+            (FoodKind::DoubleScore, 2),
+        ])
+    }
+}
+
+struct KingOfTheHillRules;
+
+impl ModeRules for KingOfTheHillRules {
+    fn label(&self) -> &'static str {
+        "King of the hill"
+    }
+
+    fn length_score(&self, _cells: usize) -> Option<u32> {
+        None
+    }
+
+    fn objective(&self) -> Option<ModeObjective> {
+        Some(ModeObjective::Hill)
+    }
+
+    /// Length doesn't score here, getting to the hill and staying on it does.
+    fn food_weights(&self) -> FoodWeights {
+        FoodWeights(vec![
+            (FoodKind::Normal, 60),
+            (FoodKind::Golden, 3),
+            (FoodKind::Shrink, 5),
+            (FoodKind::Speed, 12),
+            (FoodKind::Slow, 8),
+            (FoodKind::Ghost, 6),
+            (FoodKind::Shield, 6),
+        ])
+    }
+}
+
+struct CaptureTheFlagRules;
+
+impl ModeRules for CaptureTheFlagRules {
+    fn label(&self) -> &'static str {
+        "Capture the flag"
+    }
+
+    fn length_score(&self, _cells: usize) -> Option<u32> {
+        None
+    }
+
+    fn objective(&self) -> Option<ModeObjective> {
+        Some(ModeObjective::Flag)
+    }
+
+    /// Races for the flag, so speed to chase the carrier and slow to catch them.
+    fn food_weights(&self) -> FoodWeights {
+        FoodWeights(vec![
+            (FoodKind::Normal, 60),
+            (FoodKind::Golden, 3),
+            (FoodKind::Shrink, 4),
+            (FoodKind::Speed, 12),
+            (FoodKind::Slow, 10),
+            (FoodKind::Ghost, 6),
+            (FoodKind::Shield, 5),
+        ])
+    }
+}
+
+/// What ends a round, on top of whatever the mode itself ends it with.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum RoundLimit {
+    #[default]
+    Endless,
+    /// The round ends after this many seconds and the highest score wins.
+    Timed { seconds: f32 },
+    /// The first snake to reach this score wins.
+    ScoreTarget { score: u32 },
+}
+
+/// Limits the host can cycle through in the lobby.
+const ROUND_LIMITS: [RoundLimit; 5] = [
+    RoundLimit::Endless,
+    RoundLimit::Timed { seconds: 180.0 },
+    RoundLimit::Timed { seconds: 300.0 },
+    RoundLimit::ScoreTarget { score: 20 },
+    RoundLimit::ScoreTarget { score: 50 },
+];
+
+impl RoundLimit {
+    pub fn label(&self) -> String {
+        match *self {
+            RoundLimit::Endless => "Endless".to_string(),
+            RoundLimit::Timed { seconds } => format!("{:.0} minutes", seconds / 60.0),
+            RoundLimit::ScoreTarget { score } => format!("First to {score}"),
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = ROUND_LIMITS
+            .iter()
+            .position(|limit| limit == self)
+            .map_or(0, |index| (index + 1) % ROUND_LIMITS.len());
+        ROUND_LIMITS[index]
+    }
+}
+
+/// How fast snakes move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GameSpeed {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+}
+
+impl GameSpeed {
+    const ALL: [GameSpeed; 3] = [GameSpeed::Slow, GameSpeed::Normal, GameSpeed::Fast];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameSpeed::Slow => "Slow",
+            GameSpeed::Normal => "Normal",
+            GameSpeed::Fast => "Fast",
+        }
+    }
+
+    /// Units per second a snake covers without effects.
+    pub fn speed(&self) -> f32 {
+        match self {
+            GameSpeed::Slow => 75.0,
+            GameSpeed::Normal => 100.0,
+            GameSpeed::Fast => 130.0,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        GameSpeed::ALL[(*self as usize + 1) % GameSpeed::ALL.len()]
+    }
+}
+
+/// How big the arena is, relative to the default one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ArenaSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl ArenaSize {
+    const ALL: [ArenaSize; 3] = [ArenaSize::Small, ArenaSize::Medium, ArenaSize::Large];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ArenaSize::Small => "Small",
+            ArenaSize::Medium => "Medium",
+            ArenaSize::Large => "Large",
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        match self {
+            ArenaSize::Small => 0.7,
+            ArenaSize::Medium => 1.0,
+            ArenaSize::Large => 1.5,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        ArenaSize::ALL[(*self as usize + 1) % ArenaSize::ALL.len()]
+    }
+}
+
+/// Outline of the arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ArenaLayout {
+    #[default]
+    Circle,
+    Rectangle,
+}
+
+impl ArenaLayout {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ArenaLayout::Circle => "Round",
+            ArenaLayout::Rectangle => "Rectangle",
+        }
+    }
+
+    /// The arena at `size`, roughly as roomy whichever the outline.
+    pub fn shape(&self, size: ArenaSize) -> ArenaShape {
+        let shape = match self {
+            ArenaLayout::Circle => ArenaShape::Circle { radius: 1000.0 },
+            ArenaLayout::Rectangle => ArenaShape::Rectangle {
+                half_size: Vec2::new(1100.0, 750.0),
+            },
+        };
+        shape.scaled(size.scale())
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ArenaLayout::Circle => ArenaLayout::Rectangle,
+            ArenaLayout::Rectangle => ArenaLayout::Circle,
+        }
+    }
+}
+
+/// How much food the host keeps on the field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FoodAmount {
+    Scarce,
+    #[default]
+    Normal,
+    Plenty,
+}
+
+impl FoodAmount {
+    const ALL: [FoodAmount; 3] = [FoodAmount::Scarce, FoodAmount::Normal, FoodAmount::Plenty];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FoodAmount::Scarce => "Scarce",
+            FoodAmount::Normal => "Normal",
+            FoodAmount::Plenty => "Plenty",
+        }
+    }
+
+    pub fn density(&self) -> FoodDensity {
+        match self {
+            FoodAmount::Scarce => FoodDensity::PerPlayer {
+                per_player: 1,
+                min: 2,
+            },
+            FoodAmount::Normal => FoodDensity::PerPlayer {
+                per_player: 2,
+                min: 3,
+            },
+            FoodAmount::Plenty => FoodDensity::PerPlayer {
+                per_player: 4,
+                min: 6,
+            },
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        FoodAmount::ALL[(*self as usize + 1) % FoodAmount::ALL.len()]
+    }
+}
+
+/// Whether running into a snake's body kills. Obstacles and edges kill either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BodyCollisions {
+    #[default]
+    Lethal,
+    Harmless,
+}
+
+impl BodyCollisions {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BodyCollisions::Lethal => "Lethal",
+            BodyCollisions::Harmless => "Harmless",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            BodyCollisions::Lethal => BodyCollisions::Harmless,
+            BodyCollisions::Harmless => BodyCollisions::Lethal,
+        }
+    }
+}
+
+/// How long a freshly spawned snake can't be killed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SpawnProtection {
+    Off,
+    Short,
+    #[default]
+    Normal,
+    Long,
+}
+
+impl SpawnProtection {
+    const ALL: [SpawnProtection; 4] = [
+        SpawnProtection::Off,
+        SpawnProtection::Short,
+        SpawnProtection::Normal,
+        SpawnProtection::Long,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SpawnProtection::Off => "Off",
+            SpawnProtection::Short => "Short",
+            SpawnProtection::Normal => "Normal",
+            SpawnProtection::Long => "Long",
+        }
+    }
+
+    pub fn seconds(&self) -> f32 {
+        match self {
+            SpawnProtection::Off => 0.0,
+            SpawnProtection::Short => 1.5,
+            SpawnProtection::Normal => 3.0,
+            SpawnProtection::Long => 5.0,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        SpawnProtection::ALL[(*self as usize + 1) % SpawnProtection::ALL.len()]
+    }
+}
+
+/// How often every player sends their whole snake, on top of the moves sent as they happen.
+/// More often heals a missed move sooner at the cost of more traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SyncRate {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl SyncRate {
+    const ALL: [SyncRate; 3] = [SyncRate::Low, SyncRate::Normal, SyncRate::High];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SyncRate::Low => "Low",
+            SyncRate::Normal => "Normal",
+            SyncRate::High => "High",
+        }
+    }
+
+    /// Seconds between two full snake updates.
+    pub fn interval(&self) -> f32 {
+        match self {
+            SyncRate::Low => 1.0,
+            SyncRate::Normal => 0.5,
+            SyncRate::High => 0.25,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        SyncRate::ALL[(*self as usize + 1) % SyncRate::ALL.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchOutcome {
+    Winner(u32),
+    TeamWinner(u8),
+    /// Nobody came out on top, e.g. the last snakes went down together.
+    Draw,
+}
+
+/// End-of-round table the host sends to everyone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchResults {
+    pub outcome: MatchOutcome,
+    /// `(user_id, score)` from the highest score down.
+    pub scores: Vec<(u32, u32)>,
+}
+
+/// Which players play together, as long as there are at least two teams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TeamSettings {
+    /// Number of teams; fewer than two means everyone plays for themselves.
+    pub count: u8,
+    /// Whether running into a teammate's body kills.
+    pub lethal_teammates: bool,
+}
+
+impl Default for TeamSettings {
+    fn default() -> Self {
+        TeamSettings {
+            count: 0,
+            lethal_teammates: true,
+        }
+    }
+}
+
+impl TeamSettings {
+    pub fn enabled(&self) -> bool {
+        self.count >= 2
+    }
+}
+
+pub const TEAM_COLORS: [Color; 4] = [
+    Color::rgb(0.9, 0.2, 0.2),
+    Color::rgb(0.2, 0.4, 0.95),
+    Color::rgb(0.2, 0.8, 0.3),
+    Color::rgb(0.95, 0.8, 0.1),
+];
+
+pub const TEAM_NAMES: [&str; 4] = ["Red", "Blue", "Green", "Yellow"];
+
+/// Everything the host picks in the lobby before starting a match.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct MatchSettings {
+    pub mode: GameMode,
+    pub limit: RoundLimit,
+    pub teams: TeamSettings,
+    pub speed: GameSpeed,
+    pub arena: ArenaSize,
+    pub layout: ArenaLayout,
+    /// What leaving the arena does. Round arenas never wrap.
+    pub edge: EdgeBehaviour,
+    /// Linked portal pairs placed in the arena.
+    pub portal_pairs: usize,
+    pub food: FoodAmount,
+    pub bodies: BodyCollisions,
+    pub spawn_protection: SpawnProtection,
+    pub sync: SyncRate,
+}
+
+impl MatchSettings {
+    /// Whether heads come back in on the other side of the arena, which takes wrapping
+    /// edges, a rectangle and a mode whose edges let heads through.
+    pub fn edges_wrap(&self) -> bool {
+        let arena = Arena {
+            shape: self.layout.shape(self.arena),
+            edge: self.edge,
+            ..default()
+        };
+        !self.mode.rules().edge_kills(&arena)
+    }
+}
+
+/// The match being played, as announced by the host.
+#[derive(Resource, Default)]
+pub struct CurrentMatch {
+    pub settings: MatchSettings,
+    /// When the match started, on the shared [`GameClock`](crate::networking::GameClock).
+    pub started_at: Option<PointInTime>,
+    pub results: Option<MatchResults>,
+    /// Start of the last match left for the lobby, which the host keeps announcing so
+    /// peers still on its results screen follow.
+    pub left: Option<PointInTime>,
+}
+
+impl CurrentMatch {
+    /// Heads back to the lobby from the match that started at `started_at`. A late copy for
+    /// a match that is already over here, or older than the one being played, changes
+    /// nothing. Returns whether the match was left.
+    pub fn leave(&mut self, started_at: PointInTime) -> bool {
+        if self.started_at != Some(started_at) {
+            return false;
+        }
+        self.left = self.started_at.take();
+        self.results = None;
+        true
+    }
+}
+
+/// Sets the game up for the settings the match is played with, on every peer as it starts.
+pub fn apply_match_settings(
+    current_match: Res<CurrentMatch>,
+    mut config: ResMut<GameConfig>,
+    mut arena: ResMut<Arena>,
+    mut sync_timer: ResMut<SnakeSyncTimer>,
+) {
+    let settings = current_match.settings;
+    config.speed = settings.speed.speed();
+    config.spawn_protection = settings.spawn_protection.seconds();
+    sync_timer
+        .timer
+        .set_duration(Duration::from_secs_f32(settings.sync.interval()));
+    config.food_density = settings.food.density();
+    config.food_weights = settings.mode.rules().food_weights();
+    arena.shape = settings.layout.shape(settings.arena);
+    // Drawn solid where the mode makes them lethal anyway.
+    arena.edge = if settings.edges_wrap() {
+        EdgeBehaviour::Wrap
+    } else {
+        EdgeBehaviour::Lethal
+    };
+    arena.portal_pairs = settings.portal_pairs;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_limits_cycle_back_to_endless() {
+        let mut limit = RoundLimit::Endless;
+        for expected in ROUND_LIMITS.iter().skip(1) {
+            limit = limit.next();
+            assert_eq!(limit, *expected);
+        }
+        assert_eq!(limit.next(), RoundLimit::Endless);
+    }
+
+    #[test]
+    fn unknown_round_limit_starts_over() {
+        let limit = RoundLimit::Timed { seconds: 42.0 };
+        assert_eq!(limit.next(), RoundLimit::Endless);
+    }
+
+    #[test]
+    fn round_limit_labels() {
+        assert_eq!(RoundLimit::Timed { seconds: 180.0 }.label(), "3 minutes");
+        assert_eq!(RoundLimit::ScoreTarget { score: 20 }.label(), "First to 20");
+    }
+
+    #[test]
+    fn modes_cycle_through_all() {
+        let mut mode = GameMode::default();
+        for expected in GameMode::ALL.iter().skip(1) {
+            mode = mode.next();
+            assert_eq!(mode, *expected);
+        }
+        assert_eq!(mode.next(), GameMode::default());
+    }
+
+    #[test]
+    fn late_return_to_lobby_leaves_the_next_match_alone() {
+        let mut current_match = CurrentMatch {
+            started_at: Some(10.0),
+            ..default()
+        };
+        assert!(current_match.leave(10.0));
+        assert_eq!(current_match.started_at, None);
+        assert_eq!(current_match.left, Some(10.0));
+        // The host keeps repeating it, the lobby stays as it is.
+        assert!(!current_match.leave(10.0));
+
+        current_match.started_at = Some(60.0);
+        assert!(!current_match.leave(10.0));
+        assert_eq!(current_match.started_at, Some(60.0));
+    }
+
+    #[test]
+    fn last_snake_standing_wins_battle_royale() {
+        let rules = GameMode::BattleRoyale.rules();
+        let no_teams = |_| None;
+        assert_eq!(rules.outcome(&[], &no_teams), Some(MatchOutcome::Draw));
+        assert_eq!(rules.outcome(&[1, 2], &no_teams), None);
+        assert_eq!(
+            rules.outcome(&[2], &no_teams),
+            Some(MatchOutcome::Winner(2))
+        );
+    }
+
+    #[test]
+    fn last_team_standing_wins_battle_royale() {
+        let rules = GameMode::BattleRoyale.rules();
+        let team = |id: u32| Some((id % 2) as u8);
+        assert_eq!(rules.outcome(&[1, 2], &team), None);
+        assert_eq!(
+            rules.outcome(&[1, 3], &team),
+            Some(MatchOutcome::TeamWinner(1))
+        );
+    }
+
+    #[test]
+    fn free_play_never_ends_on_its_own() {
+        let rules = GameMode::FreePlay.rules();
+        assert!(rules.allows_respawn());
+        assert_eq!(rules.outcome(&[], &|_| None), None);
+    }
+
+    #[test]
+    fn edges_kill_unless_they_wrap() {
+        let rules = GameMode::FreePlay.rules();
+        let mut arena = Arena::default();
+        assert!(rules.edge_kills(&arena));
+        arena.edge = EdgeBehaviour::Wrap;
+        // Round arenas never wrap.
+        assert!(rules.edge_kills(&arena));
+        arena.shape = ArenaShape::Rectangle {
+            half_size: Vec2::splat(500.0),
+        };
+        assert!(!rules.edge_kills(&arena));
+    }
+
+    #[test]
+    fn battle_royale_edges_never_wrap() {
+        let mut settings = MatchSettings {
+            layout: ArenaLayout::Rectangle,
+            edge: EdgeBehaviour::Wrap,
+            ..default()
+        };
+        assert!(settings.edges_wrap());
+        settings.mode = GameMode::BattleRoyale;
+        assert!(!settings.edges_wrap());
+    }
+
+    #[test]
+    fn lobby_options_cycle_back_to_their_default() {
+        fn cycle_length<T: Copy + PartialEq + Default>(next: impl Fn(T) -> T) -> usize {
+            let mut value = next(T::default());
+            let mut steps = 1;
+            while value != T::default() {
+                value = next(value);
+                steps += 1;
+            }
+            steps
+        }
+        assert_eq!(cycle_length(|speed: GameSpeed| speed.next()), 3);
+        assert_eq!(cycle_length(|size: ArenaSize| size.next()), 3);
+        assert_eq!(cycle_length(|layout: ArenaLayout| layout.next()), 2);
+        assert_eq!(cycle_length(|food: FoodAmount| food.next()), 3);
+        assert_eq!(cycle_length(|bodies: BodyCollisions| bodies.next()), 2);
+        assert_eq!(
+            cycle_length(|protection: SpawnProtection| protection.next()),
+            4
+        );
+        assert_eq!(cycle_length(|sync: SyncRate| sync.next()), 3);
+    }
+
+    #[test]
+    fn defaults_match_the_classic_game() {
+        let settings = MatchSettings::default();
+        assert_eq!(settings.speed.speed(), 100.0);
+        assert_eq!(settings.arena.scale(), 1.0);
+        assert_eq!(settings.spawn_protection.seconds(), 3.0);
+        assert_eq!(settings.sync.interval(), 0.5);
+    }
+}
@@ -1,9 +1,11 @@
 use bevy::prelude::*;
 
 use crate::{
-    networking::{ConnectionState, SendMessage, TransportMessage},
+    arena::Arena,
+    game_mode::CurrentMatch,
+    networking::{send_for, ConnectionState, TransportMessage},
     snek::{KillSnake, SpawnSnake},
-    Head, HeadSensor, SnakeTag,
+    CellTag, HeadSensor, SnakeTag,
 };
 
 pub fn handle_kill_snake(
@@ -18,26 +20,35 @@ pub fn handle_kill_snake(
     }
 }
 
+/// Kills the snakes of the players at this machine whose head left the arena.
 pub fn check_snek_position(
-    head_sensor: Query<&GlobalTransform, With<HeadSensor>>,
+    head_sensor: Query<(&GlobalTransform, &Parent), With<HeadSensor>>,
     mut kill_write: EventWriter<KillSnake>,
-    snek_head: Query<(Entity, &SnakeTag)>,
+    heads: Query<&Parent, With<CellTag>>,
+    snakes: Query<&SnakeTag>,
+    arena: Res<Arena>,
+    current_match: Res<CurrentMatch>,
     connection_handler: Res<ConnectionState>,
 ) {
-    for transform in head_sensor.iter() {
-        const RADIUS_SQ: f32 = 1000.0 * 1000.0;
+    // Heads crossing a wrapping edge are moved back in by `teleport_heads`.
+    if !current_match.settings.mode.rules().edge_kills(&arena) {
+        return;
+    }
+    for (transform, head) in head_sensor.iter() {
         let pos = transform.translation();
-        if pos.x * pos.x + pos.y * pos.y > RADIUS_SQ {
-            if let Some(snek) = snek_head.iter().find(|p| p.1 == &SnakeTag::SelfPlayerSnake) {
-                kill_write.send(KillSnake { snake_id: snek.0 });
-                if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
-                    if let Err(err) = connection
-                        .sender
-                        .send(SendMessage::TransportMessage(TransportMessage::KillSnake))
-                    {
-                        warn!("{err:?}")
-                    }
-                }
+        if arena.contains(pos.truncate(), 0.0) {
+            continue;
+        }
+        let Ok(snake) = heads.get(head.get()).map(|head| head.get()) else {
+            continue;
+        };
+        let Ok(tag) = snakes.get(snake) else {
+            continue;
+        };
+        kill_write.send(KillSnake { snake_id: snake });
+        if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
+            if let Some(user_id) = connection.snake_owner(tag) {
+                send_for(connection, user_id, TransportMessage::KillSnake);
             }
         }
     }
@@ -51,12 +62,25 @@ pub struct RespawnButton;
 
 pub fn respawn_menu_system(
     game_over_menu: Query<Entity, With<GameOvermenu>>,
-    head: Query<&Head>,
+    snakes: Query<&SnakeTag>,
     mut commands: Commands,
     connection_handler: Res<ConnectionState>,
+    current_match: Res<CurrentMatch>,
 ) {
-    if head.is_empty() && game_over_menu.is_empty() {
+    // Spectators, and replays without an id of our own, have no snake to lose.
+    let has_snake = match connection_handler.as_ref() {
+        ConnectionState::Connected(connection) => {
+            connection.self_player().is_some_and(|p| !p.spectator)
+        }
+        ConnectionState::NotConnected => false,
+    };
+    // The players sharing our keyboard respawn from their own panes.
+    let alive = snakes
+        .iter()
+        .any(|snake| *snake == SnakeTag::SelfPlayerSnake);
+    if has_snake && !alive && game_over_menu.is_empty() {
         info!("You died");
+        let can_respawn = current_match.settings.mode.rules().allows_respawn();
         commands
             .spawn((
                 GameOvermenu,
@@ -79,7 +103,11 @@ pub fn respawn_menu_system(
             ))
             .with_children(|parent| {
                 parent.spawn(TextBundle::from_section(
-                    "You died",
+                    if can_respawn {
+                        "You died"
+                    } else {
+                        "You have been eliminated"
+                    },
                     TextStyle {
                         font_size: 50.0,
                         color: Color::rgb(0.9, 0.9, 0.9),
@@ -114,6 +142,9 @@ pub fn respawn_menu_system(
                         }
                     }
                 }
+                if !can_respawn {
+                    return;
+                }
                 parent
                     .spawn((
                         RespawnButton,
@@ -141,7 +172,7 @@ pub fn respawn_menu_system(
                         ));
                     });
             });
-    } else if !head.is_empty() && !game_over_menu.is_empty() {
+    } else if alive && !game_over_menu.is_empty() {
         if let Ok(entity) = game_over_menu.get_single() {
             commands.entity(entity).despawn_recursive();
         }
@@ -155,7 +186,7 @@ pub fn respawn_handle_button(
     for interaction in &interaction_query {
         if Interaction::Pressed == *interaction {
             info!("Spawn pressed");
-            spawn_snek_writer.send(SpawnSnake);
+            spawn_snek_writer.send(SpawnSnake { keys: None });
         }
     }
 }
@@ -0,0 +1,218 @@
+use std::time::Duration;
+
+use bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*, scene::ScenePlugin};
+use bevy_rapier2d::prelude::*;
+use bevy_tokio_tasks::TokioTasksRuntime;
+use rand::Rng;
+
+use crate::{
+    bots::{add_bot, BotDifficulty},
+    game_mode::CurrentMatch,
+    lobby::{start_match, COUNTDOWN_SECONDS},
+    networking::{connect_transport, ConnectionState, GameClock},
+    rounds::return_to_lobby,
+    spectator::Spectate,
+    GameStates, Host,
+};
+
+/// Frames per second the server simulates at.
+const TICK_RATE: f64 = 60.0;
+
+/// Seconds the lobby waits once enough players are in and ready, so friends can still join.
+const START_DELAY: f32 = 5.0;
+
+/// Seconds after which a match starts even though not everyone said they are ready.
+const FORCE_START_DELAY: f32 = 30.0;
+
+/// Seconds the results stay up before the room goes back to the lobby.
+const RESULTS_SECONDS: f32 = 10.0;
+
+const USAGE: &str = "usage: snek --headless [room] [--bots easy,normal,hard] [--min-players n]";
+
+/// What a headless peer was started with.
+#[derive(Resource, Debug, Clone)]
+pub struct HeadlessSettings {
+    room_id: String,
+    /// Bots the host keeps in the room.
+    bots: Vec<BotDifficulty>,
+    /// Players, other than bots, to wait for before starting a match.
+    min_players: usize,
+}
+
+impl HeadlessSettings {
+    /// Reads `--headless` and its options from the command line, or `None` without it.
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut args = args.peekable();
+        let mut headless = false;
+        let mut room_id = None;
+        let mut bots = vec![];
+        let mut min_players = 1;
+        let mut unknown = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => {
+                    headless = true;
+                    if args.peek().is_some_and(|next| !next.starts_with("--")) {
+                        room_id = args.next();
+                    }
+                }
+                "--bots" => {
+                    let list = args.next().ok_or(USAGE)?;
+                    for name in list.split(',') {
+                        let difficulty = BotDifficulty::ALL
+                            .into_iter()
+                            .find(|difficulty| difficulty.label().eq_ignore_ascii_case(name))
+                            .ok_or_else(|| format!("unknown bot difficulty {name}\n{USAGE}"))?;
+                        bots.push(difficulty);
+                    }
+                }
+                "--min-players" => {
+                    min_players = args
+                        .next()
+                        .and_then(|count| count.parse().ok())
+                        .ok_or(USAGE)?;
+                }
+                _ => unknown = unknown.or(Some(arg)),
+            }
+        }
+        // Anything else is for the windowed game.
+        if !headless {
+            return Ok(None);
+        }
+        if let Some(arg) = unknown {
+            return Err(format!("unknown argument {arg}\n{USAGE}"));
+        }
+        let room_id = room_id.unwrap_or_else(|| {
+            let random_number: u32 = rand::thread_rng().gen_range(100_000..1_000_000);
+            format!("{:06}", random_number)
+        });
+        Ok(Some(HeadlessSettings {
+            room_id,
+            bots,
+            min_players,
+        }))
+    }
+}
+
+/// Runs the game without a window: joins the room, hosts it if nobody else is there, and
+/// keeps matches going for whoever drops in.
+///
+/// Only the host simulates bots, so a headless peer that joins a busy room sits quietly until
+/// it inherits the room.
+pub fn run(settings: HeadlessSettings) {
+    let mut app = App::new();
+    crate::add_game_state(&mut app);
+    crate::add_gameplay_systems(&mut app);
+    // The server itself never plays.
+    app.insert_resource(settings)
+        .insert_resource(Spectate(true))
+        .add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                1.0 / TICK_RATE,
+            ))),
+            LogPlugin::default(),
+            TransformPlugin,
+            HierarchyPlugin,
+            // Rapier looks for meshes and scenes to build colliders from, even with none around.
+            AssetPlugin::default(),
+            ScenePlugin,
+            bevy_tokio_tasks::TokioTasksPlugin::default(),
+        ))
+        .add_asset::<Mesh>()
+        .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+        .add_systems(Startup, join_room)
+        .add_systems(
+            Update,
+            (
+                headless_lobby.run_if(in_state(GameStates::Lobby)),
+                headless_rematch.run_if(in_state(GameStates::GameOver)),
+            ),
+        );
+    app.run()
+}
+
+fn join_room(
+    mut commands: Commands,
+    settings: Res<HeadlessSettings>,
+    connection_handler: ResMut<ConnectionState>,
+    runtime: ResMut<TokioTasksRuntime>,
+) {
+    info!("Joining room {}", settings.room_id);
+    commands.spawn(Host);
+    connect_transport(&settings.room_id, connection_handler, runtime);
+}
+
+/// Keeps the requested bots in the room and starts a match once enough players are in.
+fn headless_lobby(
+    settings: Res<HeadlessSettings>,
+    host: Query<&Host>,
+    mut connection_handler: ResMut<ConnectionState>,
+    mut current_match: ResMut<CurrentMatch>,
+    clock: Res<GameClock>,
+    time: Res<Time>,
+    mut ready_since: Local<Option<f32>>,
+) {
+    if host.is_empty() {
+        *ready_since = None;
+        return;
+    }
+    let ConnectionState::Connected(connection) = connection_handler.as_mut() else {
+        return;
+    };
+    let Some(self_id) = connection.self_id else {
+        return;
+    };
+    let bots = connection
+        .players
+        .iter()
+        .filter(|player| player.bot.is_some())
+        .count();
+    for difficulty in settings.bots.iter().skip(bots) {
+        add_bot(connection, *difficulty);
+    }
+
+    let players = connection
+        .players
+        .iter()
+        .filter(|player| player.bot.is_none() && !player.spectator && player.user_id != self_id)
+        .count();
+    if players < settings.min_players {
+        *ready_since = None;
+        return;
+    }
+    if current_match.started_at.is_some() {
+        return;
+    }
+    let now = time.elapsed_seconds();
+    let since = *ready_since.get_or_insert(now);
+    let all_ready = connection.playing().all(|player| player.is_ready());
+    if (all_ready && now - since >= START_DELAY) || now - since >= FORCE_START_DELAY {
+        info!("Starting a match with {players} players");
+        *ready_since = None;
+        start_match(
+            connection,
+            &mut current_match,
+            clock.now(&time) + COUNTDOWN_SECONDS,
+        );
+    }
+}
+
+/// Goes back to the lobby a little while after a match ends.
+fn headless_rematch(
+    host: Query<&Host>,
+    connection_handler: Res<ConnectionState>,
+    mut current_match: ResMut<CurrentMatch>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    time: Res<Time>,
+    mut over_since: Local<Option<f32>>,
+) {
+    if host.is_empty() {
+        return;
+    }
+    let now = time.elapsed_seconds();
+    let since = *over_since.get_or_insert(now);
+    if now - since >= RESULTS_SECONDS {
+        *over_since = None;
+        return_to_lobby(&connection_handler, &mut current_match, &mut next_state);
+    }
+}
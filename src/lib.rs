@@ -1,37 +1,99 @@
+pub mod arena;
+pub mod battle_royale;
+pub mod bots;
+pub mod chat;
+pub mod effects;
+pub mod emotes;
 pub mod food;
+pub mod game_mode;
 pub mod game_over;
+#[cfg(not(target_family = "wasm"))]
+pub mod headless;
 pub mod lobby;
 pub mod menu;
 pub mod networking;
+pub mod objectives;
+pub mod obstacles;
+pub mod profile;
+#[cfg(not(target_family = "wasm"))]
+pub mod replay;
+pub mod rounds;
 pub mod scoring;
+pub mod skins;
 pub mod snek;
+pub mod spectator;
+pub mod split_screen;
+pub mod teams;
 pub mod terrain;
 pub mod window;
 
 use bevy::{
     prelude::*,
-    render::render_resource::{AddressMode, SamplerDescriptor},
+    render::{
+        render_resource::{AddressMode, SamplerDescriptor},
+        view::RenderLayers,
+    },
     sprite::Material2dPlugin,
     window::WindowResolution,
 };
+use arena::{animate_portals, spawn_portals, teleport_heads, Arena};
+use battle_royale::{clean_battle_royale, display_zone_status, setup_battle_royale, shrink_arena};
 use bevy_rapier2d::prelude::*;
-use food::{handle_food_collision, spawn_food_system, sync_food_pointer, FoodPointer};
+use bots::{
+    bot_collisions, check_bot_positions, reset_bot_lives, send_bot_snakes, spawn_bots,
+    steer_bots, BotLives,
+};
+use chat::{chat_input, display_chat, receive_chat, resend_chat, setup_chat, Chat};
+use effects::{
+    display_effects, magnet_pull_food, render_effects, setup_effects_hud, tick_active_effects,
+    ActiveEffects,
+};
+use emotes::{emote_input, receive_emotes, setup_emotes, update_emotes, EmoteState};
+use food::{
+    handle_food_collision, spawn_food_system, sync_food_pointer, FoodDensity, FoodPointer,
+    FoodWeights,
+};
+use game_mode::{apply_match_settings, CurrentMatch};
 use game_over::{
     check_snek_position, handle_kill_snake, respawn_handle_button, respawn_menu_system,
 };
-use lobby::{clean_lobby, lobby_handle_button, setup_lobby_menu, update_player_details};
-use menu::{clean_entry_menu, entry_menu, setup_menu};
+use lobby::{
+    clean_lobby, customise_handle_button, display_countdown, display_match_settings, enter_match,
+    lobby_handle_button, reset_ready, setup_lobby_menu, update_player_details, update_start_button,
+};
+use menu::{clean_entry_menu, edit_name, entry_menu, save_profile, setup_menu};
 use networking::{
-    ping_send, receive_msgs, send_snake_send, sync_add_move, update_snake, AddMove,
-    ConnectionState, PingTimer, PlayersChanged, SendMessage, SnakeSyncTimer, SnakeUpdate,
-    TransportMessage,
+    drain_offline_outbox, ping_send, receive_msgs, send_for, send_snake_send, sync_add_move,
+    update_snake, AddMove, ConnectionState, GameClock, PeerMessage, PingTimer, PlayersChanged,
+    SnakeSyncTimer, SnakeUpdate, TransportMessage,
+};
+use objectives::{
+    display_home_bases, place_flag, reset_objectives, score_hill, spawn_objectives, update_flag,
+};
+use obstacles::{rotate_obstacles, spawn_obstacles};
+use profile::{display_name_tags, sync_profile, Profile};
+use rounds::{
+    check_mode_outcome, check_round_limit, clean_gameplay, clean_results_screen,
+    display_round_status, enter_game_over, results_handle_button, setup_results_screen,
+    setup_round_hud,
 };
 use scoring::{display_scores, setup_score, sync_scores};
+use skins::{load_skin_textures, resolve_player_colors};
 use serde::{Deserialize, Serialize};
 use snek::{
-    setup_snek, spawn_snek, update_cell_direction, update_head_sensor, KillSnake, SpawnSnake,
+    apply_boost, setup_snek, spawn_snek, update_cell_direction, update_head_sensor, KillSnake,
+    SpawnSnake,
+};
+use spectator::{
+    display_spectator_status, reset_spectator_camera, spectator_camera, sync_spectating,
+    Spectate, SpectatorView,
 };
-use terrain::{setup_terrain, sync_cam, terrain_tiler, TerrainMaterial};
+use split_screen::{
+    announce_local_players, display_pane_labels, follow_local_players, respawn_local_players,
+    sync_split_screen, KeySet, MAIN_LAYER,
+};
+use teams::assign_teams;
+use terrain::{setup_terrain, sync_cam, sync_terrain_arena, terrain_tiler, TerrainMaterial};
 use window::{get_height, get_width};
 
 #[derive(Debug, Resource)]
@@ -39,6 +101,12 @@ pub struct GameConfig {
     speed: f32,
     cell_size: (f32, f32),
     game_size: (u32, u32),
+    food_density: FoodDensity,
+    food_weights: FoodWeights,
+    /// How many of the nearest off-screen food items get an edge pointer.
+    food_pointers: usize,
+    /// Seconds a new snake can't be killed by bodies, nor kill others with its own.
+    spawn_protection: f32,
 }
 
 #[derive(Bundle)]
@@ -47,8 +115,14 @@ pub struct Snake {
     spatial: SpatialBundle,
     lastmove: LastMoveId,
     moves: Moves,
+    effects: ActiveEffects,
+    speed: SnakeSpeed,
 }
 
+/// Speed of a snake in units per second, before timed effects are applied.
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
+pub struct SnakeSpeed(pub f32);
+
 #[derive(Component)]
 pub struct Host;
 
@@ -68,8 +142,12 @@ pub enum SnakeTag {
     OtherPlayerSnake(u32),
 }
 
+/// A snake steered at this machine: by its owner when `keys` is `None`, otherwise by whoever
+/// shares the keyboard using them.
 #[derive(Component)]
-pub struct Player;
+pub struct Player {
+    pub keys: Option<KeySet>,
+}
 
 #[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Direction(Vec2);
@@ -90,7 +168,9 @@ pub struct MoveId(u32);
 #[derive(Component)]
 pub struct LastMoveId(u32);
 
-pub type Move = (u32, Vec3, Direction);
+/// A point where the head changed course: move id, where it happened, the new direction,
+/// and for jumps through a wrapping edge or a portal, where the cells come out.
+pub type Move = (u32, Vec3, Direction, Option<Vec3>);
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Moves {
     moves: Vec<Move>,
@@ -121,12 +201,21 @@ pub struct ChangeDirection {
 #[derive(Component, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub struct CellTag(u32);
 
-#[derive(Event)]
+#[derive(Clone, Copy)]
 pub enum InputsActions {
     Up,
     Down,
     Left,
     Right,
+    /// Boost input pressed (`true`) or released (`false`).
+    Boost(bool),
+}
+
+/// An input for the snake of the player using `keys`, the owner's when `None`.
+#[derive(Event)]
+pub struct PlayerInput {
+    keys: Option<KeySet>,
+    action: InputsActions,
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, States, Default, Clone)]
@@ -151,14 +240,22 @@ impl GameStates {
 #[derive(Component)]
 pub struct MainCamera;
 
-#[bevy_main]
-pub fn main() {
-    let mut app = App::new();
+/// Resources, events and states every build of the game shares, windowed or not.
+fn add_game_state(app: &mut App) {
     app.insert_resource(GameConfig {
         speed: 100.0,
         cell_size: (20.0, 20.0),
         game_size: (0, 0),
+        food_density: FoodDensity::PerPlayer {
+            per_player: 2,
+            min: 3,
+        },
+        food_weights: FoodWeights::default(),
+        food_pointers: 3,
+        spawn_protection: 3.0,
     })
+    .init_resource::<Arena>()
+    .init_resource::<BotLives>()
     .insert_resource(SnakeSyncTimer {
         timer: Timer::from_seconds(0.5, TimerMode::Repeating),
     })
@@ -166,15 +263,132 @@ pub fn main() {
         timer: Timer::from_seconds(1., TimerMode::Repeating),
     })
     .insert_resource(ConnectionState::NotConnected)
+    .init_resource::<GameClock>()
+    .init_resource::<CurrentMatch>()
+    .init_resource::<Spectate>()
+    .insert_resource(Profile::load())
+    .init_resource::<Chat>()
+    .init_resource::<EmoteState>()
+    .init_resource::<SpectatorView>()
     .add_state::<GameStates>()
     .add_event::<ChangeDirection>()
-    .add_event::<InputsActions>()
+    .add_event::<PlayerInput>()
     .add_event::<SnakeUpdate>()
     .add_event::<AddMove>()
     .add_event::<PlayersChanged>()
+    .add_event::<PeerMessage>()
     .add_event::<KillSnake>()
-    .add_event::<SpawnSnake>()
-    .add_plugins((
+    .add_event::<SpawnSnake>();
+}
+
+/// Systems that play the match itself, shared by the windowed game and `headless::run` so
+/// both simulate it the same way. Whatever draws or reads the keyboard is added on top by the
+/// windowed game.
+fn add_gameplay_systems(app: &mut App) {
+    app.add_systems(
+        OnEnter(GameStates::GamePlay),
+        (
+            apply_match_settings,
+            (
+                spawn_obstacles,
+                spawn_portals,
+                setup_battle_royale,
+                reset_objectives,
+                reset_bot_lives,
+            ),
+        )
+            .chain(),
+    )
+    .add_systems(
+        OnExit(GameStates::GamePlay),
+        (clean_battle_royale, clean_gameplay),
+    )
+    .add_systems(OnEnter(GameStates::Lobby), reset_ready)
+    .add_systems(
+        Update,
+        enter_match.run_if(in_state(GameStates::Lobby).or_else(in_state(GameStates::GameOver))),
+    )
+    .add_systems(
+        Update,
+        (
+            update_cell_direction,
+            move_cells.before(handle_food_collision),
+            handle_input_event,
+            teleport_heads,
+            update_head_sensor,
+            spawn_food_system,
+            handle_food_collision,
+            spawn_snek,
+            apply_boost,
+            tick_active_effects,
+            magnet_pull_food,
+            rotate_obstacles,
+        )
+            .chain()
+            .run_if(in_state(GameStates::GamePlay)),
+    )
+    .add_systems(
+        Update,
+        (
+            receive_msgs,
+            ping_send,
+            announce_local_players.after(ping_send),
+            drain_offline_outbox,
+            assign_teams,
+            sync_spectating,
+            receive_chat,
+            resend_chat,
+            handle_kill_snake,
+            check_snek_position,
+            send_snake_send.run_if(in_state(GameStates::GamePlay)),
+            (update_snake, sync_add_move, sync_scores).run_if(in_state(GameStates::GamePlay)),
+        ),
+    )
+    .add_systems(
+        Update,
+        (
+            shrink_arena,
+            check_mode_outcome,
+            check_round_limit,
+            enter_game_over,
+        )
+            .run_if(in_state(GameStates::GamePlay)),
+    )
+    .add_systems(
+        Update,
+        (spawn_objectives, score_hill, update_flag, place_flag)
+            .chain()
+            .run_if(in_state(GameStates::GamePlay)),
+    )
+    .add_systems(
+        Update,
+        (
+            spawn_bots,
+            steer_bots,
+            bot_collisions,
+            check_bot_positions,
+            send_bot_snakes,
+        )
+            .run_if(in_state(GameStates::GamePlay)),
+    );
+}
+
+#[bevy_main]
+pub fn main() {
+    #[cfg(not(target_family = "wasm"))]
+    match headless::HeadlessSettings::from_args(std::env::args().skip(1)) {
+        Ok(Some(settings)) => return headless::run(settings),
+        Ok(None) => {}
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(2);
+        }
+    }
+
+    let mut app = App::new();
+    add_game_state(&mut app);
+    add_gameplay_systems(&mut app);
+    app.add_plugins((
         DefaultPlugins
             .set(WindowPlugin {
                 primary_window: Some(Window {
@@ -195,50 +409,88 @@ pub fn main() {
         Material2dPlugin::<TerrainMaterial>::default(),
     ))
     .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-    .add_systems(Startup, setup)
+    .add_systems(Startup, (setup, load_skin_textures, setup_chat))
     .add_systems(OnEnter(GameStates::EntryMenu), setup_menu)
-    .add_systems(OnEnter(GameStates::GamePlay), (setup_snek, setup_score))
-    .add_systems(OnExit(GameStates::EntryMenu), clean_entry_menu)
-    .add_systems(Update, entry_menu.run_if(in_state(GameStates::EntryMenu)))
-    .add_systems(OnEnter(GameStates::Lobby), setup_lobby_menu)
+    .add_systems(
+        OnEnter(GameStates::GamePlay),
+        (
+            setup_snek,
+            setup_score,
+            setup_effects_hud,
+            setup_round_hud,
+            setup_emotes,
+        )
+            .after(apply_match_settings),
+    )
+    .add_systems(OnExit(GameStates::GamePlay), reset_spectator_camera)
+    .add_systems(OnEnter(GameStates::GameOver), setup_results_screen)
+    .add_systems(OnExit(GameStates::GameOver), clean_results_screen)
+    .add_systems(
+        Update,
+        results_handle_button.run_if(in_state(GameStates::GameOver)),
+    )
+    .add_systems(
+        OnExit(GameStates::EntryMenu),
+        (clean_entry_menu, save_profile),
+    )
+    .add_systems(
+        Update,
+        (entry_menu, edit_name).run_if(in_state(GameStates::EntryMenu)),
+    )
+    .add_systems(
+        OnEnter(GameStates::Lobby),
+        setup_lobby_menu.after(reset_ready),
+    )
     .add_systems(OnExit(GameStates::Lobby), clean_lobby)
     .add_systems(
         Update,
-        (lobby_handle_button, update_player_details).run_if(in_state(GameStates::Lobby)),
+        (
+            lobby_handle_button,
+            customise_handle_button,
+            update_player_details,
+            update_start_button,
+            display_countdown,
+            display_match_settings,
+        )
+            .run_if(in_state(GameStates::Lobby)),
     )
     .add_systems(
         Update,
         (
+            (keyboard_input, handle_touch).before(handle_input_event),
             (
-                update_cell_direction,
-                move_cells.before(handle_food_collision),
-                keyboard_input,
-                handle_touch,
-                handle_input_event,
-                update_head_sensor,
-                spawn_food_system,
-                handle_food_collision,
-                spawn_snek,
                 display_scores,
+                render_effects,
+                display_effects,
+                animate_portals,
             )
-                .run_if(in_state(GameStates::GamePlay)),
-            sync_cam,
+                .after(magnet_pull_food),
+            sync_food_pointer,
+            respawn_menu_system,
+            respawn_handle_button,
+            display_zone_status,
+            display_round_status,
+            display_home_bases.after(place_flag),
+            respawn_local_players,
         )
-            .chain(),
+            .run_if(in_state(GameStates::GamePlay)),
     )
     .add_systems(
         Update,
         (
-            receive_msgs,
-            ping_send,
-            send_snake_send.run_if(in_state(GameStates::GamePlay)),
+            sync_cam.after(rotate_obstacles),
+            sync_profile,
+            resolve_player_colors,
+            chat_input
+                .run_if(in_state(GameStates::Lobby).or_else(in_state(GameStates::GamePlay))),
+            display_chat,
             (
-                update_snake,
-                sync_add_move,
-                sync_food_pointer,
-                respawn_menu_system,
-                respawn_handle_button,
-                sync_scores,
+                spectator_camera.after(sync_cam),
+                display_spectator_status,
+                display_name_tags.after(sync_cam),
+                (emote_input, receive_emotes, update_emotes)
+                    .chain()
+                    .after(sync_cam),
             )
                 .run_if(in_state(GameStates::GamePlay)),
         ),
@@ -246,7 +498,13 @@ pub fn main() {
     .add_systems(Startup, setup_terrain)
     .add_systems(
         Update,
-        (terrain_tiler, handle_kill_snake, check_snek_position),
+        (
+            terrain_tiler,
+            sync_terrain_arena,
+            sync_split_screen,
+            follow_local_players,
+            display_pane_labels,
+        ),
     );
 
     #[cfg(debug_assertions)]
@@ -255,6 +513,9 @@ pub fn main() {
     #[cfg(not(target_family = "wasm"))]
     setup_tokio(&mut app);
 
+    #[cfg(not(target_family = "wasm"))]
+    setup_replays(&mut app);
+
     #[cfg(target_family = "wasm")]
     remove_wasm_loader();
 
@@ -285,6 +546,36 @@ fn setup_tokio(app: &mut App) {
     app.add_plugins(bevy_tokio_tasks::TokioTasksPlugin::default());
 }
 
+/// Every match gets recorded unless `--no-replays` is given, and `--replay <file>` plays one
+/// back instead of showing the menu.
+#[cfg(not(target_family = "wasm"))]
+fn setup_replays(app: &mut App) {
+    use replay::{
+        control_replay, display_replay_status, move_free_camera, play_replay, record_traffic,
+        start_recording, start_replay, stop_recording, ReplayFile, ReplaySettings,
+    };
+
+    app.insert_resource(ReplaySettings::from_args(std::env::args().skip(1)));
+    if let Some(file) = ReplayFile::from_args(std::env::args().skip(1)) {
+        app.insert_resource(file);
+    }
+    app.add_systems(PostStartup, start_replay)
+        .add_systems(OnEnter(GameStates::GamePlay), start_recording)
+        .add_systems(OnExit(GameStates::GamePlay), stop_recording)
+        .add_systems(
+            Update,
+            (
+                record_traffic,
+                control_replay,
+                play_replay,
+                move_free_camera,
+                display_replay_status,
+            )
+                .chain()
+                .before(receive_msgs),
+        );
+}
+
 fn setup(
     mut config: ResMut<GameConfig>,
     mut commands: Commands,
@@ -292,136 +583,192 @@ fn setup(
     asset_server: Res<AssetServer>,
 ) {
     commands.spawn(Host);
-    commands.spawn((
-        SpriteBundle {
-            texture: asset_server.load("red-arrow.png"),
-            sprite: Sprite {
-                custom_size: Some(Vec2 { x: 57.0, y: 45.0 }),
-                ..Default::default()
+    for rank in 0..config.food_pointers {
+        // Pointers to food further away are drawn smaller and fainter.
+        let scale = 1.0 / (1.0 + rank as f32 * 0.35);
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load("red-arrow.png"),
+                sprite: Sprite {
+                    color: Color::rgba(1.0, 1.0, 1.0, scale),
+                    custom_size: Some(Vec2 {
+                        x: 57.0 * scale,
+                        y: 45.0 * scale,
+                    }),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(Vec3::new(0., 0., 2.)),
+                visibility: Visibility::Hidden,
+                ..default()
             },
-            transform: Transform::from_translation(Vec3::new(0., 0., 2.)),
-            visibility: Visibility::Hidden,
-            ..default()
-        },
-        FoodPointer,
-    ));
+            FoodPointer(rank),
+            RenderLayers::layer(MAIN_LAYER),
+        ));
+    }
     let window = window.single();
     config.game_size = (
         window.resolution.width() as u32,
         window.resolution.height() as u32,
     );
-    commands.spawn(Camera2dBundle {
-        camera: Camera {
-            order: 0,
+    commands.spawn((
+        MainCamera,
+        RenderLayers::from_layers(&[0, MAIN_LAYER]),
+        Camera2dBundle {
+            camera: Camera {
+                order: 0,
+                ..Default::default()
+            },
             ..Default::default()
         },
-        ..Default::default()
-    });
+    ));
 }
 
 fn move_cells(
-    mut query: Query<(&mut Transform, &Direction)>,
+    mut query: Query<(&mut Transform, &Direction, &Parent)>,
+    snakes: Query<(&SnakeSpeed, &ActiveEffects)>,
     time: Res<Time>,
-    config: Res<GameConfig>,
 ) {
-    for (mut transform, direction) in query.iter_mut() {
+    for (mut transform, direction, parent) in query.iter_mut() {
         let direction = Vec3 {
             x: direction.0.x,
             y: direction.0.y,
             z: 0.0,
         };
-        transform.translation += time.delta_seconds() * (config.speed) * direction;
+        let Ok((speed, effects)) = snakes.get(parent.get()) else {
+            continue;
+        };
+        let speed = speed.0 * effects.speed_multiplier();
+        transform.translation += time.delta_seconds() * speed * direction;
     }
 }
 
 fn handle_input_event(
-    mut event: EventReader<InputsActions>,
-    mut query: Query<(Entity, &mut LastMoveId, &mut Moves), With<Player>>,
-    mut head: Query<(&Parent, &Transform, &mut Direction, &mut MoveId, Entity), With<Head>>,
+    mut events: EventReader<PlayerInput>,
+    mut players: Query<(&Player, &SnakeTag, &Children, &mut LastMoveId, &mut Moves)>,
+    heads: Query<(&Transform, &Direction), With<Head>>,
     mut ev_change_direction: EventWriter<ChangeDirection>,
     connection_handler: Res<ConnectionState>,
     time: Res<Time>,
 ) {
-    let Some(event) = event.iter().next() else {
-        return;
-    };
-    let direction = match event {
-        InputsActions::Up => Vec2 { x: 0.0, y: 1.0 },
-        InputsActions::Down => Vec2 { x: 0.0, y: -1.0 },
-        InputsActions::Left => Vec2 { x: -1.0, y: 0.0 },
-        InputsActions::Right => Vec2 { x: 1.0, y: 0.0 },
-    };
-
-    let Ok(val) = query.get_single_mut() else {
-        return;
-    };
-    let player_id = val.0;
-    let mut last_move = val.1;
-    let mut moves = val.2;
-    for (parent, head, head_direction, _, head_id) in head.iter_mut() {
-        if parent.get() == player_id
-            && head_direction.0 != direction
-            && (head_direction.0 + direction) != Vec2::ZERO
-        {
-            last_move.0 += 1;
-            let _move = (
-                last_move.0,
-                head.translation
-                    + Vec3 {
-                        x: head_direction.0.x,
-                        y: head_direction.0.y,
-                        z: 0.0,
-                    },
-                Direction(direction),
-            );
-            moves.moves.push(_move.clone());
-            if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
-                if let Err(err) = connection.sender.send(SendMessage::TransportMessage(
+    // One turn per snake and frame, the head hasn't taken the first one yet.
+    let mut turned = vec![];
+    for input in events.iter() {
+        let direction = match input.action {
+            InputsActions::Up => Vec2 { x: 0.0, y: 1.0 },
+            InputsActions::Down => Vec2 { x: 0.0, y: -1.0 },
+            InputsActions::Left => Vec2 { x: -1.0, y: 0.0 },
+            InputsActions::Right => Vec2 { x: 1.0, y: 0.0 },
+            InputsActions::Boost(_) => continue,
+        };
+        if turned.contains(&input.keys) {
+            continue;
+        }
+        let Some((_, tag, children, mut last_move, mut moves)) = players
+            .iter_mut()
+            .find(|(player, ..)| player.keys == input.keys)
+        else {
+            continue;
+        };
+        let Some((head_id, (head, head_direction))) = children
+            .iter()
+            .find_map(|cell| heads.get(*cell).ok().map(|head| (*cell, head)))
+        else {
+            continue;
+        };
+        if head_direction.0 == direction || (head_direction.0 + direction) == Vec2::ZERO {
+            continue;
+        }
+        turned.push(input.keys);
+        last_move.0 += 1;
+        let _move = (
+            last_move.0,
+            head.translation
+                + Vec3 {
+                    x: head_direction.0.x,
+                    y: head_direction.0.y,
+                    z: 0.0,
+                },
+            Direction(direction),
+            None,
+        );
+        moves.moves.push(_move.clone());
+        if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
+            if let Some(user_id) = connection.snake_owner(tag) {
+                send_for(
+                    connection,
+                    user_id,
                     TransportMessage::AddMove(time.elapsed_seconds(), _move),
-                )) {
-                    warn!("{err:?}")
-                }
+                );
             }
-            ev_change_direction.send(ChangeDirection {
-                head: head_id,
-                direction,
-            });
-
-            // head_direction.0 = direction;
-            // moveid.0 = last_move.0;
         }
+        ev_change_direction.send(ChangeDirection {
+            head: head_id,
+            direction,
+        });
     }
 }
 
-fn keyboard_input(keys: Res<Input<KeyCode>>, mut event: EventWriter<InputsActions>) {
-    if keys.just_pressed(KeyCode::Up) {
-        event.send(InputsActions::Up);
-    } else if keys.just_pressed(KeyCode::Down) {
-        event.send(InputsActions::Down);
-    } else if keys.just_pressed(KeyCode::Left) {
-        event.send(InputsActions::Left);
-    } else if keys.just_pressed(KeyCode::Right) {
-        event.send(InputsActions::Right);
-    } else {
-        return;
+/// The arrows and space steer the owner's snake, every [`KeySet`] the snake of whoever sits
+/// at it. Nothing steers while the chat input is open, but letting go of boost still counts.
+fn keyboard_input(
+    keys: Res<Input<KeyCode>>,
+    chat: Res<Chat>,
+    mut event: EventWriter<PlayerInput>,
+) {
+    let typing = chat.is_typing();
+    let arrows = [KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right];
+    let seats = [(None, arrows, KeyCode::Space)].into_iter().chain(
+        KeySet::ALL.map(|seat| (Some(seat), seat.keys(), seat.boost_key())),
+    );
+    for (seat, [up, down, left, right], boost) in seats {
+        let mut send = |action| event.send(PlayerInput { keys: seat, action });
+        if keys.just_released(boost) {
+            send(InputsActions::Boost(false));
+        }
+        if typing {
+            continue;
+        }
+        if keys.just_pressed(boost) {
+            send(InputsActions::Boost(true));
+        }
+        if keys.just_pressed(up) {
+            send(InputsActions::Up);
+        } else if keys.just_pressed(down) {
+            send(InputsActions::Down);
+        } else if keys.just_pressed(left) {
+            send(InputsActions::Left);
+        } else if keys.just_pressed(right) {
+            send(InputsActions::Right);
+        }
     }
 }
 
-fn handle_touch(touch_event: Res<Touches>, mut event: EventWriter<InputsActions>) {
+fn handle_touch(
+    touch_event: Res<Touches>,
+    mut event: EventWriter<PlayerInput>,
+    mut boosting: Local<bool>,
+) {
+    let mut send = |action| event.send(PlayerInput { keys: None, action });
+    // Holding two fingers down boosts.
+    let two_fingers = touch_event.iter().count() >= 2;
+    if two_fingers != *boosting {
+        *boosting = two_fingers;
+        send(InputsActions::Boost(two_fingers));
+    }
     for touch in touch_event.iter_just_released() {
         let distance = touch.distance();
         const THRESHOLD: f32 = 50.0;
         if distance.x.abs() > distance.y.abs() && distance.x.abs() > THRESHOLD {
             if distance.x > 0. {
-                event.send(InputsActions::Right);
+                send(InputsActions::Right);
             } else {
-                event.send(InputsActions::Left);
+                send(InputsActions::Left);
             }
         } else if distance.y.abs() > THRESHOLD {
             if distance.y > 0. {
-                event.send(InputsActions::Down);
+                send(InputsActions::Down);
             } else {
-                event.send(InputsActions::Up);
+                send(InputsActions::Up);
             }
         }
     }
@@ -1,7 +1,18 @@
 use bevy::prelude::*;
 
 use crate::{
-    networking::{ConnectionState, PlayerProp, PlayersChanged},
+    arena::{EdgeBehaviour, MAX_PORTAL_PAIRS},
+    bots::{add_bot, remove_bot, BotDifficulty},
+    game_mode::{ArenaLayout, CurrentMatch, MatchSettings},
+    networking::{
+        ConnectionHandler, ConnectionState, GameClock, PlayerProp, PlayersChanged, PointInTime,
+        SendMessage, TransportMessage, OFFLINE_ROOM_ID,
+    },
+    profile::Profile,
+    skins::{Skin, PALETTE},
+    spectator::Spectate,
+    split_screen::{add_local_player, remove_local_player},
+    teams::team_name,
     GameStates, Host,
 };
 
@@ -14,11 +25,185 @@ pub struct PlayersNode;
 #[derive(Component)]
 pub struct PlayerNode(PlayerProp);
 
+/// Seconds between the host starting the match and everyone's snakes setting off.
+pub const COUNTDOWN_SECONDS: f32 = 3.0;
+
 #[derive(Component)]
 pub struct StartButton;
 
-pub fn setup_lobby_menu(mut commands: Commands, connection_handler: Res<ConnectionState>) {
+#[derive(Component)]
+pub struct StartButtonText;
+
+/// Tells the room whether we are ready to play.
+#[derive(Component)]
+pub struct ReadyButton;
+
+/// Dims the lobby while counting down to the start.
+#[derive(Component)]
+pub struct CountdownOverlay;
+
+/// The 3-2-1 shown over the lobby once the host started the match.
+#[derive(Component)]
+pub struct CountdownText;
+
+/// Switches between playing and watching, for everyone in the lobby.
+#[derive(Component)]
+pub struct SpectateButton;
+
+/// Picks the colour at this index of the [`PALETTE`].
+#[derive(Component)]
+pub struct ColorButton(u8);
+
+#[derive(Component)]
+pub struct SkinButton(Skin);
+
+/// A match setting the host can cycle through with a button.
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum MatchOption {
+    Mode,
+    Round,
+    Teams,
+    Teammates,
+    Speed,
+    Arena,
+    Layout,
+    Edges,
+    Portals,
+    Food,
+    Bodies,
+    Protection,
+    Sync,
+}
+
+impl MatchOption {
+    const ALL: [MatchOption; 13] = [
+        MatchOption::Mode,
+        MatchOption::Round,
+        MatchOption::Teams,
+        MatchOption::Teammates,
+        MatchOption::Speed,
+        MatchOption::Arena,
+        MatchOption::Layout,
+        MatchOption::Edges,
+        MatchOption::Portals,
+        MatchOption::Food,
+        MatchOption::Bodies,
+        MatchOption::Protection,
+        MatchOption::Sync,
+    ];
+
+    fn label(&self, settings: &MatchSettings) -> String {
+        match self {
+            MatchOption::Mode => format!("Mode: {}", settings.mode.label()),
+            MatchOption::Round => format!("Round: {}", settings.limit.label()),
+            MatchOption::Teams if settings.teams.enabled() => {
+                format!("Teams: {}", settings.teams.count)
+            }
+            MatchOption::Teams => "Teams: Off".to_string(),
+            MatchOption::Teammates if settings.teams.lethal_teammates => {
+                "Teammates: Lethal".to_string()
+            }
+            MatchOption::Teammates => "Teammates: Harmless".to_string(),
+            MatchOption::Speed => format!("Speed: {}", settings.speed.label()),
+            MatchOption::Arena => format!("Arena: {}", settings.arena.label()),
+            MatchOption::Layout => format!("Shape: {}", settings.layout.label()),
+            MatchOption::Edges
+                if settings.edge == EdgeBehaviour::Wrap
+                    && settings.layout == ArenaLayout::Circle =>
+            {
+                "Edges: Wrap (rectangle only)".to_string()
+            }
+            MatchOption::Edges
+                if settings.edge == EdgeBehaviour::Wrap && !settings.edges_wrap() =>
+            {
+                format!("Edges: Wrap (not in {})", settings.mode.label())
+            }
+            MatchOption::Edges => format!("Edges: {}", settings.edge.label()),
+            MatchOption::Portals if settings.portal_pairs == 0 => "Portals: Off".to_string(),
+            MatchOption::Portals => format!("Portals: {}", settings.portal_pairs),
+            MatchOption::Food => format!("Food: {}", settings.food.label()),
+            MatchOption::Bodies => format!("Bodies: {}", settings.bodies.label()),
+            MatchOption::Protection => {
+                format!("Spawn protection: {}", settings.spawn_protection.label())
+            }
+            MatchOption::Sync => format!("Sync rate: {}", settings.sync.label()),
+        }
+    }
+
+    fn cycle(&self, settings: &mut MatchSettings) {
+        match self {
+            MatchOption::Mode => settings.mode = settings.mode.next(),
+            MatchOption::Round => settings.limit = settings.limit.next(),
+            MatchOption::Teams => {
+                settings.teams.count = match settings.teams.count {
+                    0 | 1 => 2,
+                    count if count >= 4 => 0,
+                    count => count + 1,
+                }
+            }
+            MatchOption::Teammates => {
+                settings.teams.lethal_teammates = !settings.teams.lethal_teammates
+            }
+            MatchOption::Speed => settings.speed = settings.speed.next(),
+            MatchOption::Arena => settings.arena = settings.arena.next(),
+            MatchOption::Layout => settings.layout = settings.layout.next(),
+            MatchOption::Edges => settings.edge = settings.edge.next(),
+            MatchOption::Portals => {
+                settings.portal_pairs = (settings.portal_pairs + 1) % (MAX_PORTAL_PAIRS + 1)
+            }
+            MatchOption::Food => settings.food = settings.food.next(),
+            MatchOption::Bodies => settings.bodies = settings.bodies.next(),
+            MatchOption::Protection => settings.spawn_protection = settings.spawn_protection.next(),
+            MatchOption::Sync => settings.sync = settings.sync.next(),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct MatchOptionText(MatchOption);
+
+/// Holds the match options: buttons for the host, read-only for everyone else.
+#[derive(Component)]
+pub struct SettingsPanel {
+    editable: bool,
+}
+
+/// Buttons to fill the room up with bots, for the host, or to seat more players at our
+/// keyboard, for everyone playing. Both take them out again too.
+#[derive(Component, Clone, Copy)]
+pub enum BotButton {
+    Add(BotDifficulty),
+    Remove,
+    AddLocal,
+    RemoveLocal,
+}
+
+impl BotButton {
+    fn label(&self) -> String {
+        match self {
+            BotButton::Add(difficulty) => format!("+ {} bot", difficulty.label()),
+            BotButton::Remove => "- Bot".to_string(),
+            BotButton::AddLocal => "+ Local player".to_string(),
+            BotButton::RemoveLocal => "- Local player".to_string(),
+        }
+    }
+}
+
+/// The host's row of [`BotButton`]s for bots.
+#[derive(Component)]
+pub struct BotButtons;
+
+pub fn setup_lobby_menu(
+    mut commands: Commands,
+    connection_handler: Res<ConnectionState>,
+    mut players_changed: EventWriter<PlayersChanged>,
+) {
     if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
+        // Coming back from a round nobody joins or leaves, so fill the list in right away.
+        players_changed.send(PlayersChanged {
+            players: connection.players.clone(),
+            self_player: connection.self_id,
+        });
         commands
             .spawn((
                 LobbyMainNode,
@@ -39,7 +224,11 @@ pub fn setup_lobby_menu(mut commands: Commands, connection_handler: Res<Connecti
             ))
             .with_children(|parent| {
                 parent.spawn(TextBundle::from_section(
-                    format!("Room {}", connection.room_id),
+                    if connection.room_id == OFFLINE_ROOM_ID {
+                        "Offline".to_string()
+                    } else {
+                        format!("Room {}", connection.room_id)
+                    },
                     TextStyle {
                         font_size: 40.0,
                         color: Color::rgb(0.9, 0.9, 0.9),
@@ -89,15 +278,17 @@ pub fn setup_lobby_menu(mut commands: Commands, connection_handler: Res<Connecti
 pub fn update_player_details(
     lobby_query: Query<Entity, With<LobbyMainNode>>,
     players_node: Query<(Entity, &PlayersNode)>,
-    game_button: Query<Entity, With<StartButton>>,
+    game_button: Query<Entity, Or<(With<StartButton>, With<BotButtons>)>>,
+    settings_panel: Query<(Entity, &SettingsPanel)>,
     host: Query<Entity, With<Host>>,
+    current_match: Res<CurrentMatch>,
     mut players_changed: EventReader<PlayersChanged>,
     mut commands: Commands,
 ) {
-    if let Some(player_ev) = players_changed.iter().next() {
+    if let Some(player_ev) = players_changed.iter().last() {
         let players_node: (Entity, &PlayersNode) = players_node.single();
         commands.entity(players_node.0).despawn_descendants();
-        for player in player_ev.players.iter() {
+        for player in player_ev.players.iter().filter(|p| !p.spectator) {
             let node = commands
                 .spawn((
                     PlayerNode(player.clone()),
@@ -107,7 +298,7 @@ pub fn update_player_details(
                 ))
                 .with_children(|parent| {
                     parent.spawn(NodeBundle {
-                        background_color: player.color.into(),
+                        background_color: player.snake_color().into(),
                         style: Style {
                             width: Val::Px(30.),
                             height: Val::Px(30.),
@@ -118,12 +309,21 @@ pub fn update_player_details(
                     });
                     parent.spawn(TextBundle::from_section(
                         format!(
-                            "Player {}{}",
-                            player.user_id,
+                            "{}{}{}{}",
+                            player.name(),
                             if Some(player.user_id) == player_ev.self_player {
                                 " (You)"
                             } else {
                                 ""
+                            },
+                            player
+                                .team
+                                .map(|team| format!(" - {} team", team_name(team)))
+                                .unwrap_or_default(),
+                            if player.bot.is_none() && player.ready {
+                                " - Ready"
+                            } else {
+                                ""
                             }
                         ),
                         TextStyle {
@@ -136,11 +336,127 @@ pub fn update_player_details(
                 .id();
             commands.get_entity(players_node.0).unwrap().add_child(node);
         }
+        let spectators = player_ev.players.iter().filter(|p| p.spectator).count();
+        let spectating = player_ev
+            .players
+            .iter()
+            .any(|p| p.spectator && Some(p.user_id) == player_ev.self_player);
+        commands.entity(players_node.0).with_children(|parent| {
+            if spectators > 0 {
+                parent.spawn(TextBundle::from_section(
+                    format!(
+                        "{spectators} watching{}",
+                        if spectating { " (You)" } else { "" }
+                    ),
+                    TextStyle {
+                        font_size: 24.0,
+                        color: Color::rgb(0.7, 0.7, 0.7),
+                        ..default()
+                    },
+                ));
+            }
+            parent
+                .spawn((
+                    SpectateButton,
+                    ButtonBundle {
+                        style: Style {
+                            height: Val::Px(40.),
+                            margin: UiRect::top(Val::Px(10.)),
+                            padding: UiRect::horizontal(Val::Px(10.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        if spectating { "Play" } else { "Spectate" },
+                        TextStyle {
+                            font_size: 24.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                            ..default()
+                        },
+                    ));
+                });
+        });
+        let self_player = player_ev
+            .players
+            .iter()
+            .find(|p| Some(p.user_id) == player_ev.self_player && !p.spectator);
+        if let Some(self_player) = self_player {
+            let ready = commands
+                .spawn((
+                    ReadyButton,
+                    ButtonBundle {
+                        style: Style {
+                            height: Val::Px(40.),
+                            margin: UiRect::top(Val::Px(10.)),
+                            padding: UiRect::horizontal(Val::Px(10.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: if self_player.ready {
+                            Color::rgb(0.2, 0.5, 0.2)
+                        } else {
+                            Color::rgb(0.15, 0.15, 0.15)
+                        }
+                        .into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        if self_player.ready {
+                            "Not ready"
+                        } else {
+                            "Ready"
+                        },
+                        TextStyle {
+                            font_size: 24.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                            ..default()
+                        },
+                    ));
+                })
+                .id();
+            commands.entity(players_node.0).add_child(ready);
+            let pickers = spawn_customise_buttons(&mut commands, self_player, &player_ev.players);
+            commands.entity(players_node.0).push_children(&pickers);
+            let local_buttons = spawn_button_row(
+                &mut commands,
+                &[BotButton::AddLocal, BotButton::RemoveLocal],
+            );
+            commands.entity(players_node.0).add_child(local_buttons);
+        }
+        // Rebuilt when the host moves, so only the host can edit.
+        let editable = !host.is_empty();
+        if !settings_panel
+            .iter()
+            .any(|(_, panel)| panel.editable == editable)
+        {
+            for (panel, _) in settings_panel.iter() {
+                commands.entity(panel).despawn_recursive();
+            }
+            let panel = spawn_settings_panel(&mut commands, &current_match.settings, editable);
+            commands.entity(lobby_query.single()).add_child(panel);
+        }
         if host.is_empty() {
             for button in game_button.iter() {
                 commands.entity(button).despawn_recursive();
             }
         } else if game_button.is_empty() {
+            let bot_buttons: Vec<BotButton> = BotDifficulty::ALL
+                .iter()
+                .map(|difficulty| BotButton::Add(*difficulty))
+                .chain([BotButton::Remove])
+                .collect();
+            let bot_buttons = spawn_button_row(&mut commands, &bot_buttons);
+            commands.entity(bot_buttons).insert(BotButtons);
+            commands.entity(lobby_query.single()).add_child(bot_buttons);
             let id = commands
                 .spawn((
                     StartButton,
@@ -157,48 +473,615 @@ pub fn update_player_details(
                         ..default()
                     },
                 ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        StartButtonText,
+                        TextBundle::from_section(
+                            "Start Game",
+                            TextStyle {
+                                font_size: 40.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                                ..default()
+                            },
+                        ),
+                    ));
+                })
+                .id();
+            commands.entity(lobby_query.single()).add_child(id);
+        }
+    }
+}
+
+/// Rows of colours and skins for the player to pick from. Colours someone else has are dimmed,
+/// and our own is outlined.
+fn spawn_customise_buttons(
+    commands: &mut Commands,
+    self_player: &PlayerProp,
+    players: &[PlayerProp],
+) -> [Entity; 2] {
+    let row = |commands: &mut Commands| {
+        commands
+            .spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    column_gap: Val::Px(5.),
+                    row_gap: Val::Px(5.),
+                    margin: UiRect::top(Val::Px(10.)),
+                    ..default()
+                },
+                ..default()
+            })
+            .id()
+    };
+    let colors = row(commands);
+    commands.entity(colors).with_children(|parent| {
+        for (index, color) in PALETTE.iter().enumerate() {
+            let taken = players
+                .iter()
+                .any(|p| p.user_id != self_player.user_id && !p.spectator && p.color == *color);
+            let mine = self_player.color == *color;
+            parent.spawn((
+                ColorButton(index as u8),
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Px(30.),
+                        height: Val::Px(30.),
+                        border: UiRect::all(Val::Px(if mine { 3. } else { 0. })),
+                        ..default()
+                    },
+                    border_color: Color::WHITE.into(),
+                    background_color: color.with_a(if taken { 0.25 } else { 1.0 }).into(),
+                    ..default()
+                },
+            ));
+        }
+    });
+    let skins = row(commands);
+    commands.entity(skins).with_children(|parent| {
+        for skin in Skin::ALL {
+            parent
+                .spawn((
+                    SkinButton(skin),
+                    ButtonBundle {
+                        style: Style {
+                            height: Val::Px(35.),
+                            padding: UiRect::horizontal(Val::Px(10.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: if self_player.profile.skin == skin {
+                            Color::rgb(0.35, 0.35, 0.35)
+                        } else {
+                            Color::rgb(0.15, 0.15, 0.15)
+                        }
+                        .into(),
+                        ..default()
+                    },
+                ))
                 .with_children(|parent| {
                     parent.spawn(TextBundle::from_section(
-                        "Start Game",
+                        skin.label(),
                         TextStyle {
-                            font_size: 40.0,
+                            font_size: 20.0,
                             color: Color::rgb(0.9, 0.9, 0.9),
                             ..default()
                         },
                     ));
-                })
-                .id();
-            commands.entity(lobby_query.single()).add_child(id);
+                });
+        }
+    });
+    [colors, skins]
+}
+
+/// Picking a colour or skin in the lobby, kept for next time too.
+pub fn customise_handle_button(
+    color_interaction: Query<(&Interaction, &ColorButton), Changed<Interaction>>,
+    skin_interaction: Query<(&Interaction, &SkinButton), Changed<Interaction>>,
+    mut profile: ResMut<Profile>,
+) {
+    let mut changed = false;
+    for (interaction, button) in &color_interaction {
+        if Interaction::Pressed == *interaction && profile.color != Some(button.0) {
+            profile.color = Some(button.0);
+            changed = true;
+        }
+    }
+    for (interaction, button) in &skin_interaction {
+        if Interaction::Pressed == *interaction && profile.skin != button.0 {
+            profile.skin = button.0;
+            changed = true;
         }
     }
+    if changed {
+        profile.save();
+    }
+}
+
+/// Every match option in a wrapping row, as buttons the host cycles through or as plain labels
+/// for everyone else.
+fn spawn_settings_panel(
+    commands: &mut Commands,
+    settings: &MatchSettings,
+    editable: bool,
+) -> Entity {
+    let options: Vec<Entity> = MatchOption::ALL
+        .iter()
+        .map(|option| spawn_option(commands, *option, settings, editable))
+        .collect();
+    commands
+        .spawn((
+            SettingsPanel { editable },
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    max_width: Val::Percent(90.),
+                    column_gap: Val::Px(10.),
+                    row_gap: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .push_children(&options)
+        .id()
+}
+
+/// Shows the current value of a match option.
+fn spawn_option(
+    commands: &mut Commands,
+    option: MatchOption,
+    settings: &MatchSettings,
+    editable: bool,
+) -> Entity {
+    let style = Style {
+        height: Val::Px(50.),
+        padding: UiRect::horizontal(Val::Px(10.)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let mut node = if editable {
+        commands.spawn((
+            option,
+            ButtonBundle {
+                style,
+                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                ..default()
+            },
+        ))
+    } else {
+        commands.spawn(NodeBundle {
+            style,
+            background_color: Color::rgba(0.15, 0.15, 0.15, 0.5).into(),
+            ..default()
+        })
+    };
+    node.with_children(|parent| {
+        parent.spawn((
+            MatchOptionText(option),
+            TextBundle::from_section(
+                option.label(settings),
+                TextStyle {
+                    font_size: 30.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ),
+        ));
+    });
+    node.id()
+}
+
+/// Keeps the option labels in step with the settings, whether the host changed them here or
+/// they came in from the host.
+pub fn display_match_settings(
+    current_match: Res<CurrentMatch>,
+    mut option_text: Query<(&mut Text, &MatchOptionText)>,
+) {
+    if !current_match.is_changed() {
+        return;
+    }
+    for (mut text, option) in option_text.iter_mut() {
+        let label = option.0.label(&current_match.settings);
+        if let Some(section) = text.sections.first_mut() {
+            if section.value != label {
+                section.value = label;
+            }
+        }
+    }
+}
+
+fn spawn_button_row(commands: &mut Commands, buttons: &[BotButton]) -> Entity {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                column_gap: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for button in buttons {
+                parent
+                    .spawn((
+                        *button,
+                        ButtonBundle {
+                            style: Style {
+                                height: Val::Px(50.),
+                                padding: UiRect::horizontal(Val::Px(10.)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            button.label(),
+                            TextStyle {
+                                font_size: 30.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        })
+        .id()
 }
 
 pub fn lobby_handle_button(
-    mut next_state: ResMut<NextState<GameStates>>,
     interaction_query: Query<&Interaction, (Changed<Interaction>, With<StartButton>)>,
-    connection_handler: ResMut<ConnectionState>,
+    option_interaction: Query<(&Interaction, &MatchOption), Changed<Interaction>>,
+    bot_interaction: Query<(&Interaction, &BotButton), Changed<Interaction>>,
+    spectate_interaction: Query<&Interaction, (Changed<Interaction>, With<SpectateButton>)>,
+    ready_interaction: Query<&Interaction, (Changed<Interaction>, With<ReadyButton>)>,
+    mut spectate: ResMut<Spectate>,
+    mut current_match: ResMut<CurrentMatch>,
+    mut connection_handler: ResMut<ConnectionState>,
+    mut players_changed: EventWriter<PlayersChanged>,
+    clock: Res<GameClock>,
     time: Res<Time>,
 ) {
-    for interaction in &interaction_query {
+    for interaction in &spectate_interaction {
         if Interaction::Pressed == *interaction {
+            spectate.0 = !spectate.0;
+        }
+    }
+    for interaction in &ready_interaction {
+        if Interaction::Pressed != *interaction {
+            continue;
+        }
+        if let ConnectionState::Connected(connection) = connection_handler.as_mut() {
+            let self_id = connection.self_id;
+            let player = connection
+                .players
+                .iter_mut()
+                .find(|p| Some(p.user_id) == self_id);
+            if let Some(player) = player {
+                player.ready = !player.ready;
+                let ready = player.ready;
+                send_ready(connection, ready);
+            }
+            players_changed.send(PlayersChanged {
+                players: connection.players.clone(),
+                self_player: connection.self_id,
+            });
+        }
+    }
+    for (interaction, button) in &bot_interaction {
+        if Interaction::Pressed != *interaction {
+            continue;
+        }
+        if let ConnectionState::Connected(connection) = connection_handler.as_mut() {
+            match button {
+                BotButton::Add(difficulty) => add_bot(connection, *difficulty),
+                BotButton::Remove => remove_bot(connection),
+                BotButton::AddLocal => add_local_player(connection),
+                BotButton::RemoveLocal => remove_local_player(connection),
+            }
+            players_changed.send(PlayersChanged {
+                players: connection.players.clone(),
+                self_player: connection.self_id,
+            });
+        }
+    }
+    for (interaction, option) in &option_interaction {
+        // The match already went out with the settings it is played with.
+        if Interaction::Pressed != *interaction || counting_down(&current_match) {
+            continue;
+        }
+        option.cycle(&mut current_match.settings);
+        if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
+            if let Err(err) = connection.sender.send(SendMessage::TransportMessage(
+                TransportMessage::LobbySettings(current_match.settings),
+            )) {
+                warn!("{err:?}")
+            }
+        }
+    }
+    for interaction in &interaction_query {
+        // Pressing it while not everyone is ready forces the start, the button says as much.
+        if Interaction::Pressed == *interaction && !counting_down(&current_match) {
             if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
-                if let Err(err) =
-                    connection
-                        .sender
-                        .send(crate::networking::SendMessage::TransportMessage(
-                            crate::networking::TransportMessage::StartGame(time.elapsed_seconds()),
-                        ))
-                {
-                    warn!("{err:?}")
+                start_match(
+                    connection,
+                    &mut current_match,
+                    clock.now(&time) + COUNTDOWN_SECONDS,
+                );
+            }
+        }
+    }
+}
+
+fn send_ready(connection: &ConnectionHandler, ready: bool) {
+    if let Err(err) =
+        connection
+            .sender
+            .send(SendMessage::TransportMessage(TransportMessage::Ready(
+                ready,
+            )))
+    {
+        warn!("{err:?}")
+    }
+}
+
+/// Whether the host started a match that hasn't been entered yet.
+fn counting_down(current_match: &CurrentMatch) -> bool {
+    current_match.started_at.is_some() && current_match.results.is_none()
+}
+
+/// Starts the match with the current settings, on the host. Everyone, the host included, enters
+/// it at `started_at`, see [`enter_match`].
+pub fn start_match(
+    connection: &ConnectionHandler,
+    current_match: &mut CurrentMatch,
+    started_at: PointInTime,
+) {
+    current_match.started_at = Some(started_at);
+    current_match.results = None;
+    if let Err(err) =
+        connection
+            .sender
+            .send(SendMessage::TransportMessage(TransportMessage::StartGame(
+                started_at,
+                current_match.settings,
+            )))
+    {
+        warn!("{err:?}")
+    }
+}
+
+/// Everyone has to say they are ready again after each match.
+pub fn reset_ready(
+    mut connection_handler: ResMut<ConnectionState>,
+    mut players_changed: EventWriter<PlayersChanged>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_mut() else {
+        return;
+    };
+    for player in connection.players.iter_mut() {
+        player.ready = false;
+    }
+    send_ready(connection, false);
+    players_changed.send(PlayersChanged {
+        players: connection.players.clone(),
+        self_player: connection.self_id,
+    });
+}
+
+/// Shows the host whether the start button starts the match or forces it.
+pub fn update_start_button(
+    mut text: Query<&mut Text, With<StartButtonText>>,
+    connection_handler: Res<ConnectionState>,
+    current_match: Res<CurrentMatch>,
+) {
+    let (Ok(mut text), ConnectionState::Connected(connection)) =
+        (text.get_single_mut(), connection_handler.as_ref())
+    else {
+        return;
+    };
+    // Pressing start says the host is ready.
+    let not_ready = connection
+        .playing()
+        .filter(|p| Some(p.user_id) != connection.self_id && !p.is_ready())
+        .count();
+    let label = if counting_down(&current_match) {
+        "Starting...".to_string()
+    } else if not_ready > 0 {
+        format!("Force Start ({not_ready} not ready)")
+    } else {
+        "Start Game".to_string()
+    };
+    if let Some(section) = text.sections.first_mut() {
+        if section.value != label {
+            section.value = label;
+        }
+    }
+}
+
+/// Goes into the match once its start time comes around on the shared clock.
+pub fn enter_match(
+    current_match: Res<CurrentMatch>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    clock: Res<GameClock>,
+    time: Res<Time>,
+) {
+    let Some(started_at) = current_match.started_at else {
+        return;
+    };
+    if current_match.results.is_none() && clock.now(&time) >= started_at {
+        next_state.set(GameStates::GamePlay);
+    }
+}
+
+/// Counts down to the start of the match over the lobby.
+pub fn display_countdown(
+    mut commands: Commands,
+    overlay: Query<Entity, With<CountdownOverlay>>,
+    mut text: Query<&mut Text, With<CountdownText>>,
+    current_match: Res<CurrentMatch>,
+    clock: Res<GameClock>,
+    time: Res<Time>,
+) {
+    let left = current_match
+        .started_at
+        .filter(|_| current_match.results.is_none())
+        .map(|started_at| started_at - clock.now(&time))
+        .filter(|left| *left > 0.0);
+    let Some(left) = left else {
+        for entity in overlay.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    let value = format!("{}", left.ceil() as u32);
+    match text.get_single_mut() {
+        Ok(mut text) => {
+            if let Some(section) = text.sections.first_mut() {
+                if section.value != value {
+                    section.value = value;
                 }
-                next_state.set(GameStates::GamePlay);
             }
         }
+        Err(_) => {
+            commands
+                .spawn((
+                    CountdownOverlay,
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            width: Val::Percent(100.),
+                            height: Val::Percent(100.),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+                        z_index: ZIndex::Global(10),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        CountdownText,
+                        TextBundle::from_section(
+                            value,
+                            TextStyle {
+                                font_size: 160.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                                ..default()
+                            },
+                        ),
+                    ));
+                });
+        }
     }
 }
 
-pub fn clean_lobby(lobby_query: Query<Entity, With<LobbyMainNode>>, mut commands: Commands) {
+pub fn clean_lobby(
+    lobby_query: Query<Entity, Or<(With<LobbyMainNode>, With<CountdownOverlay>)>>,
+    mut commands: Commands,
+) {
     for lobby_node in lobby_query.iter() {
         commands.entity(lobby_node).despawn_recursive();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::split_screen::{KeySet, Seat};
+
+    fn player(user_id: u32) -> PlayerProp {
+        PlayerProp {
+            last_update_time: None,
+            start_time: None,
+            user_id,
+            color: Color::WHITE,
+            score: 0,
+            highest_score: 0,
+            team: None,
+            bot: None,
+            seat: None,
+            spectator: false,
+            profile: Profile::default(),
+            ready: false,
+        }
+    }
+
+    #[test]
+    fn only_people_at_their_own_keyboard_have_to_say_ready() {
+        let mut host = player(1);
+        assert!(!host.is_ready());
+        host.ready = true;
+        assert!(host.is_ready());
+        let bot = PlayerProp {
+            bot: Some(BotDifficulty::Easy),
+            ..player(2)
+        };
+        assert!(bot.is_ready());
+        let seated = PlayerProp {
+            seat: Some(Seat {
+                owner: 1,
+                keys: KeySet::Wasd,
+            }),
+            ..player(3)
+        };
+        assert!(seated.is_ready());
+    }
+
+    #[test]
+    fn start_match_tells_everyone_when_to_go() {
+        let (sender, outbox) = flume::unbounded();
+        let (_, receiver) = flume::unbounded();
+        let connection = ConnectionHandler {
+            self_id: Some(1),
+            room_id: OFFLINE_ROOM_ID.to_string(),
+            players: vec![player(1)],
+            sender,
+            receiver,
+        };
+        let mut current_match = CurrentMatch::default();
+        start_match(&connection, &mut current_match, 13.0);
+        assert!(counting_down(&current_match));
+        let Ok(SendMessage::TransportMessage(TransportMessage::StartGame(started_at, _))) =
+            outbox.try_recv()
+        else {
+            panic!("no StartGame went out");
+        };
+        assert_eq!(started_at, 13.0);
+    }
+
+    #[test]
+    fn enter_match_waits_for_the_countdown() {
+        let mut world = World::new();
+        world.insert_resource(CurrentMatch {
+            started_at: Some(COUNTDOWN_SECONDS),
+            ..default()
+        });
+        world.insert_resource(GameClock::default());
+        world.insert_resource(Time::default());
+        world.insert_resource(NextState::<GameStates>::default());
+        let mut schedule = Schedule::new();
+        schedule.add_systems(enter_match);
+
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<NextState<GameStates>>().0, None);
+
+        world.resource_mut::<CurrentMatch>().started_at = Some(0.0);
+        schedule.run(&mut world);
+        assert_eq!(
+            world.resource::<NextState<GameStates>>().0,
+            Some(GameStates::GamePlay)
+        );
+    }
+}
@@ -1,4 +1,9 @@
-use crate::networking::{connect_transport, ConnectionState};
+use crate::{
+    networking::{connect_transport, start_offline, ConnectionState},
+    profile::{clean_name, Profile, MAX_NAME_LENGTH},
+    spectator::Spectate,
+    GameStates,
+};
 
 use bevy::prelude::*;
 
@@ -18,6 +23,9 @@ pub struct HostButton;
 #[derive(Component)]
 pub struct JoinButton;
 
+#[derive(Component)]
+pub struct OfflineButton;
+
 #[derive(Component)]
 pub struct TypeButton(String);
 
@@ -27,10 +35,19 @@ pub struct BackButton;
 #[derive(Component)]
 pub struct RoomIdInputField;
 
+/// Shows the name being typed on the entry menu.
 #[derive(Component)]
-pub struct JoinRoomSubmitButton;
+pub struct NameInputField;
+
+fn name_label(name: &str) -> String {
+    format!("Name: {name}_")
+}
 
-pub fn setup_menu(mut commands: Commands) {
+/// Joins the typed in room, only to watch when set.
+#[derive(Component)]
+pub struct JoinRoomSubmitButton(bool);
+
+pub fn setup_menu(mut commands: Commands, profile: Res<Profile>) {
     let _button_entity = commands
         .spawn((
             EntryMenuNode,
@@ -65,6 +82,17 @@ pub fn setup_menu(mut commands: Commands) {
                     ..default()
                 },
             ));
+            parent.spawn((
+                NameInputField,
+                TextBundle::from_section(
+                    name_label(&profile.name),
+                    TextStyle {
+                        font_size: 30.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ),
+            ));
             parent
                 .spawn((
                     HostJoinButtonsContainer,
@@ -136,6 +164,33 @@ pub fn setup_menu(mut commands: Commands) {
                                 },
                             ));
                         });
+                    parent
+                        .spawn((
+                            OfflineButton,
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(150.),
+                                    height: Val::Px(65.),
+                                    // horizontally center child text
+                                    justify_content: JustifyContent::Center,
+                                    // vertically center child text
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                                ..default()
+                            },
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "Offline",
+                                TextStyle {
+                                    font_size: 30.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                    ..default()
+                                },
+                            ));
+                        });
                 });
         })
         .id();
@@ -146,13 +201,16 @@ pub fn entry_menu(
     interaction_query: Query<(Entity, &Interaction), Changed<Interaction>>,
     host_button: Query<Entity, With<HostButton>>,
     join_button: Query<Entity, With<JoinButton>>,
+    offline_button: Query<Entity, With<OfflineButton>>,
     mut commands: Commands,
-    connection_handler: ResMut<ConnectionState>,
+    mut connection_handler: ResMut<ConnectionState>,
+    mut next_state: ResMut<NextState<GameStates>>,
     q_entry_menu_node: Query<Entity, With<EntryMenuNode>>,
     q_text_button: Query<&TypeButton>,
     q_back_button: Query<&BackButton>,
     q_join_submit_button: Query<&JoinRoomSubmitButton>,
     mut room_input: Query<&mut Text, With<RoomIdInputField>>,
+    mut spectate: ResMut<Spectate>,
     asset_server: Res<AssetServer>,
     #[cfg(not(target_family = "wasm"))] runtime: ResMut<TokioTasksRuntime>,
 ) {
@@ -162,6 +220,7 @@ pub fn entry_menu(
                 let mut rng = rand::thread_rng();
                 let random_number: u32 = rng.gen_range(100_000..1_000_000);
                 let random_string = format!("{:06}", random_number);
+                spectate.0 = false;
 
                 connect_transport(
                     &random_string,
@@ -170,6 +229,11 @@ pub fn entry_menu(
                     runtime,
                 );
                 break;
+            } else if offline_button.get(interaction.0).is_ok() {
+                spectate.0 = false;
+                start_offline(&mut commands, &mut connection_handler);
+                next_state.set(GameStates::Lobby);
+                break;
             } else if join_button.get(interaction.0).is_ok() {
                 for q in q_host_join_container.iter() {
                     commands.entity(q).despawn_recursive();
@@ -199,10 +263,11 @@ pub fn entry_menu(
                         input.sections = vec![new_section];
                     }
                 }
-            } else if let Ok(_but) = q_join_submit_button.get(interaction.0) {
+            } else if let Ok(submit) = q_join_submit_button.get(interaction.0) {
                 if let Ok(input) = room_input.get_single_mut() {
                     if let Some(section) = input.sections.first() {
                         if section.value.len() == 6 {
+                            spectate.0 = submit.0;
                             connect_transport(
                                 &section.value,
                                 connection_handler,
@@ -369,7 +434,7 @@ pub fn setup_numpad(parent: Entity, asset_server: Res<AssetServer>, commands: &m
                             background_color: Color::rgb(0.15, 0.15, 0.15).into(),
                             ..Default::default()
                         },
-                        JoinRoomSubmitButton,
+                        JoinRoomSubmitButton(false),
                     ))
                     .with_children(|parent| {
                         parent.spawn((
@@ -386,9 +451,68 @@ pub fn setup_numpad(parent: Entity, asset_server: Res<AssetServer>, commands: &m
                         ));
                     });
             });
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        max_width: Val::Px(300.0),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::vertical(Val::Px(10.)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                    ..Default::default()
+                },
+                JoinRoomSubmitButton(true),
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "Watch",
+                    TextStyle {
+                        font_size: 30.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ));
+            });
     });
 }
 
+/// Typing anywhere on the entry menu edits the player's name.
+pub fn edit_name(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut profile: ResMut<Profile>,
+    mut name_input: Query<&mut Text, With<NameInputField>>,
+) {
+    let mut name = profile.name.clone();
+    for event in characters.iter() {
+        if !event.char.is_control() && name.chars().count() < MAX_NAME_LENGTH {
+            name.push(event.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        name.pop();
+    }
+    if name == profile.name {
+        return;
+    }
+    for mut text in name_input.iter_mut() {
+        if let Some(section) = text.sections.first_mut() {
+            section.value = name_label(&name);
+        }
+    }
+    profile.name = name;
+}
+
+/// Keeps the name for next time once the player moves on from the menu.
+pub fn save_profile(mut profile: ResMut<Profile>) {
+    profile.name = clean_name(&profile.name);
+    profile.save();
+}
+
 pub fn clean_entry_menu(menu_query: Query<Entity, With<EntryMenuNode>>, mut commands: Commands) {
     for menu_node in menu_query.iter() {
         commands.entity(menu_node).despawn_recursive();
@@ -13,17 +13,25 @@ use serde::{Deserialize, Serialize};
 use bevy_tokio_tasks::TokioTasksRuntime;
 
 use crate::{
-    food::{spawn_food, Food},
+    bots::{bot_roster, is_bot, sync_bot_roster, BotDifficulty},
+    effects::ActiveEffects,
+    emotes::Emote,
+    food::{spawn_food, Food, FoodKind},
+    game_mode::{CurrentMatch, MatchResults, MatchSettings},
+    objectives::{Flag, FlagState},
+    profile::Profile,
     snek::KillSnake,
-    CellTag, Direction, GameConfig, GameStates, Host, LastMoveId, Move, MoveId, Moves, Snake,
-    SnakeCell, SnakeTag,
+    split_screen::{is_local_player_of, sync_local_players, KeySet, Seat},
+    teams::{team_assignments, team_color},
+    CellTag, Direction, GameConfig, GameStates, Host, LastMoveId, Move, MoveId, Moves, Player,
+    Snake, SnakeCell, SnakeSpeed, SnakeTag,
 };
 
 pub enum SendMessage {
     TransportMessage(TransportMessage),
 }
 
-type PointInTime = f32;
+pub type PointInTime = f32;
 
 #[derive(Serialize, Deserialize)]
 pub enum TransportMessage {
@@ -31,12 +39,49 @@ pub enum TransportMessage {
     // InformPlayers(Vec<PlayerProp>),
     SnakeUpdate(PointInTime, SnakeDetails),
     AddMove(PointInTime, Move),
-    StartGame(PointInTime),
-    SpawnFood(u32, Vec2),
+    /// Match start on the host's [`GameClock`] and the settings it is played with. The start is
+    /// a few seconds ahead, so everyone counts down and begins together.
+    StartGame(PointInTime, MatchSettings),
+    /// The settings the host picked so far, repeated while in the lobby so everyone sees them.
+    LobbySettings(MatchSettings),
+    SpawnFood(u32, Vec2, FoodKind),
     KillSnake,
     DespawnFood(u32),
+    EffectsChanged(ActiveEffects),
     Ping(f32),
     Pong(f32),
+    /// The host's [`GameClock`] reading, broadcast so peers can line their clocks up with it.
+    ClockSync(PointInTime),
+    MatchOver(MatchResults),
+    /// The host left the results of the match that started at this time, everyone heads back
+    /// to the lobby for a rematch.
+    ReturnToLobby(PointInTime),
+    /// Team of every player, `None` when teams are off.
+    TeamAssignments(Vec<(u32, Option<u8>)>),
+    /// The sender's score in modes scored by objectives.
+    ScoreChanged(u32),
+    FlagMoved(FlagState),
+    /// Every bot in the room, sent by the host.
+    Bots(Vec<(u32, BotDifficulty)>),
+    /// A message the host sends on behalf of one of its bots, or anyone on behalf of a player
+    /// sharing their keyboard.
+    OnBehalf(u32, Box<TransportMessage>),
+    /// Keys of the players sharing the sender's keyboard, in the order they joined. Repeated
+    /// for peers joining later.
+    LocalPlayers(Vec<KeySet>),
+    /// Whether the sender only watches. Repeated while it does, for peers joining later.
+    Spectating(bool),
+    /// Whether the sender is ready for the match to start. Repeated while it is.
+    Ready(bool),
+    /// The sender's name and looks, repeated for peers joining later.
+    Profile(Profile),
+    /// A chat line, numbered by its sender and resent until everyone acknowledged it.
+    Chat(u32, String),
+    /// Received the chat line with this number from this user.
+    ChatAck(u32, u32),
+    Emote(Emote),
+    /// Marks a spot on the map for everyone.
+    MapPing(Vec2),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -45,6 +90,8 @@ pub struct SnakeDetails {
     moves: Moves,
     // spawners: Spawner,
     cells: Vec<SnakeCellDetails>,
+    effects: ActiveEffects,
+    speed: SnakeSpeed,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -55,7 +102,7 @@ pub struct SnakeCellDetails {
     direction: crate::Direction,
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum RelayMessage {
     RoomJoined(u32, Vec<u32>),
     UserConnected(u32, Vec<u32>),
@@ -87,6 +134,49 @@ pub struct PingTimer {
     pub timer: Timer,
 }
 
+/// Clock shared by everyone in the room, following the host's.
+///
+/// Anything that has to look the same on every peer at the same moment, like moving
+/// obstacles, should be driven by [`GameClock::now`] instead of the local [`Time`].
+#[derive(Resource, Default)]
+pub struct GameClock {
+    offset: Option<f32>,
+    /// Who the clock readings come from.
+    host_id: Option<u32>,
+    /// Ping round trip to the host, in seconds.
+    round_trip: Option<f32>,
+}
+
+impl GameClock {
+    pub fn now(&self, time: &Time) -> PointInTime {
+        time.elapsed_seconds() + self.offset.unwrap_or_default()
+    }
+
+    fn sync(&mut self, host_id: u32, host_time: PointInTime, time: &Time) {
+        if self.host_id != Some(host_id) {
+            self.host_id = Some(host_id);
+            self.round_trip = None;
+        }
+        // The reading took about half a round trip to get here.
+        let latency = self.round_trip.unwrap_or_default() / 2.;
+        let offset = host_time + latency - time.elapsed_seconds();
+        // Smooth out jitter from varying latency once we have a first reading.
+        self.offset = Some(match self.offset {
+            Some(current) => current * 0.8 + offset * 0.2,
+            None => offset,
+        });
+    }
+
+    fn measured_round_trip(&mut self, user_id: u32, round_trip: f32) {
+        if self.host_id == Some(user_id) {
+            self.round_trip = Some(match self.round_trip {
+                Some(current) => current * 0.8 + round_trip * 0.2,
+                None => round_trip,
+            });
+        }
+    }
+}
+
 pub struct ConnectionHandler {
     pub self_id: Option<u32>,
     pub room_id: String,
@@ -95,6 +185,26 @@ pub struct ConnectionHandler {
     pub receiver: Receiver<ReceiveMessage>,
 }
 
+impl ConnectionHandler {
+    pub fn self_player(&self) -> Option<&PlayerProp> {
+        let self_id = self.self_id?;
+        self.players.iter().find(|p| p.user_id == self_id)
+    }
+
+    /// User the snake with `tag` belongs to.
+    pub fn snake_owner(&self, tag: &SnakeTag) -> Option<u32> {
+        match tag {
+            SnakeTag::SelfPlayerSnake => self.self_id,
+            SnakeTag::OtherPlayerSnake(id) => Some(*id),
+        }
+    }
+
+    /// Everyone with a snake in the game, leaving out spectators.
+    pub fn playing(&self) -> impl Iterator<Item = &PlayerProp> {
+        self.players.iter().filter(|p| !p.spectator)
+    }
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlayerProp {
     pub last_update_time: Option<PointInTime>,
@@ -103,6 +213,48 @@ pub struct PlayerProp {
     pub color: Color,
     pub score: u32,
     pub highest_score: u32,
+    pub team: Option<u8>,
+    /// Set for snakes the host plays itself.
+    pub bot: Option<BotDifficulty>,
+    /// Set for players sharing the keyboard of someone else in the room.
+    pub seat: Option<Seat>,
+    /// Watches the game without ever spawning a snake.
+    pub spectator: bool,
+    pub profile: Profile,
+    /// Said they are ready for the host to start the match.
+    pub ready: bool,
+}
+
+impl PlayerProp {
+    /// Bots, and players at someone else's keyboard, are always ready.
+    pub fn is_ready(&self) -> bool {
+        self.ready || self.bot.is_some() || self.seat.is_some()
+    }
+
+    pub fn name(&self) -> String {
+        match self.bot {
+            Some(difficulty) => format!("{} bot", difficulty.label()),
+            None if !self.profile.name.is_empty() => self.profile.name.clone(),
+            None => format!("Player {}", self.user_id),
+        }
+    }
+
+    /// Colour the player's snake is drawn in: the team's colour while playing in teams.
+    pub fn snake_color(&self) -> Color {
+        self.team.map_or(self.color, team_color)
+    }
+}
+
+/// Colour a player gets by default, the same on every peer.
+pub fn player_color(user_id: u32) -> Color {
+    Color::Hsla {
+        hue: seeded_random::Random::from_seed(seeded_random::Seed::unsafe_new(user_id as u64))
+            .gen::<f32>()
+            * 360.,
+        saturation: 1.,
+        lightness: 0.5,
+        alpha: 1.,
+    }
 }
 
 #[derive(Event)]
@@ -124,6 +276,13 @@ pub struct AddMove {
     _move: Move,
 }
 
+/// A message from a peer that is handled by systems outside [`receive_msgs`].
+#[derive(Event)]
+pub struct PeerMessage {
+    pub user_id: u32,
+    pub message: TransportMessage,
+}
+
 #[derive(Component)]
 pub struct ReceivedMsgTask(Task<ReceiveMessage>);
 
@@ -173,6 +332,52 @@ pub fn connect_transport(
     });
 }
 
+/// Room id used while playing offline.
+pub const OFFLINE_ROOM_ID: &str = "offline";
+
+/// Id the local player gets while playing offline.
+const OFFLINE_USER_ID: u32 = 1;
+
+/// Messages sent while playing offline, which nobody is there to receive.
+#[derive(Resource)]
+pub struct OfflineOutbox(Receiver<SendMessage>);
+
+/// Sets up a room with nobody else in it and no relay behind it.
+///
+/// Everything runs as it does online with us as the host; whatever gets sent piles up
+/// in the [`OfflineOutbox`] until [`drain_offline_outbox`] throws it away.
+pub fn start_offline(commands: &mut Commands, connection_handler: &mut ConnectionState) {
+    let (sender_tx, sender_rx) = flume::unbounded();
+    let (_receiver_tx, receiver_rx) = flume::unbounded();
+    *connection_handler = ConnectionState::Connected(ConnectionHandler {
+        self_id: Some(OFFLINE_USER_ID),
+        players: vec![PlayerProp {
+            last_update_time: None,
+            start_time: None,
+            user_id: OFFLINE_USER_ID,
+            color: player_color(OFFLINE_USER_ID),
+            score: 0,
+            highest_score: 0,
+            team: None,
+            bot: None,
+            seat: None,
+            spectator: false,
+            ready: false,
+            profile: Profile::default(),
+        }],
+        sender: sender_tx,
+        receiver: receiver_rx,
+        room_id: OFFLINE_ROOM_ID.to_string(),
+    });
+    commands.insert_resource(OfflineOutbox(sender_rx));
+}
+
+pub fn drain_offline_outbox(outbox: Option<Res<OfflineOutbox>>) {
+    if let Some(outbox) = outbox {
+        outbox.0.drain();
+    }
+}
+
 async fn send_receive_background(
     room_id_c: String,
     #[cfg(not(target_family = "wasm"))] endpoint: wtransport::Endpoint<
@@ -274,17 +479,23 @@ pub fn receive_msgs(
     mut snake_update: EventWriter<SnakeUpdate>,
     mut add_move: EventWriter<AddMove>,
     mut players_changed_ev: EventWriter<PlayersChanged>,
+    mut peer_messages: EventWriter<PeerMessage>,
     host: Query<Entity, With<Host>>,
-    food: Query<(Entity, &Food)>,
+    food: Query<(Entity, &Food, &FoodKind, &Transform)>,
     mut commands: Commands,
     time: Res<Time>,
     mut snake_killer: EventWriter<KillSnake>,
     snakes: Query<(Entity, &SnakeTag)>,
+    mut clock: ResMut<GameClock>,
+    mut current_match: ResMut<CurrentMatch>,
 ) {
     match connection_handler.as_mut() {
         ConnectionState::NotConnected => {}
         ConnectionState::Connected(connection) => {
-            if let Some(msg) = connection.receiver.try_iter().next() {
+            // Everything queued since the last frame. A replay that is skipping ahead queues
+            // a lot at once.
+            let pending: Vec<ReceiveMessage> = connection.receiver.try_iter().collect();
+            for msg in pending {
                 print!("Connection established");
                 match msg {
                     ReceiveMessage::ConnectionEstablished => {
@@ -305,16 +516,7 @@ pub fn receive_msgs(
                                     info!("Joined room with id {}", user_id);
                                     connection.self_id = Some(user_id);
                                     if connection.players.is_empty() {
-                                        let color = Color::Hsla {
-                                            hue: seeded_random::Random::from_seed(
-                                                seeded_random::Seed::unsafe_new(user_id as u64),
-                                            )
-                                            .gen::<f32>()
-                                                * 360.,
-                                            saturation: 1.,
-                                            lightness: 0.5,
-                                            alpha: 1.,
-                                        };
+                                        let color = player_color(user_id);
                                         connection.players.push(PlayerProp {
                                             last_update_time: None,
                                             user_id,
@@ -322,19 +524,16 @@ pub fn receive_msgs(
                                             start_time: None,
                                             score: 0,
                                             highest_score: 0,
+                                            team: None,
+                                            bot: None,
+                                            seat: None,
+                                            spectator: false,
+                                            ready: false,
+                                            profile: Profile::default(),
                                         });
                                     }
                                     for user in users.iter() {
-                                        let color = Color::Hsla {
-                                            hue: seeded_random::Random::from_seed(
-                                                seeded_random::Seed::unsafe_new(*user as u64),
-                                            )
-                                            .gen::<f32>()
-                                                * 360.,
-                                            saturation: 1.,
-                                            lightness: 0.5,
-                                            alpha: 1.,
-                                        };
+                                        let color = player_color(*user);
                                         connection.players.push(PlayerProp {
                                             user_id: *user,
                                             color,
@@ -342,6 +541,12 @@ pub fn receive_msgs(
                                             last_update_time: None,
                                             score: 0,
                                             highest_score: 0,
+                                            team: None,
+                                            bot: None,
+                                            seat: None,
+                                            spectator: false,
+                                            ready: false,
+                                            profile: Profile::default(),
                                         });
                                         players_changed_ev.send(PlayersChanged {
                                             players: connection.players.clone(),
@@ -360,16 +565,7 @@ pub fn receive_msgs(
                                 }
                                 RelayMessage::UserConnected(id, _users) => {
                                     info!("User connected {id}");
-                                    let color = Color::Hsla {
-                                        hue: seeded_random::Random::from_seed(
-                                            seeded_random::Seed::unsafe_new(id as u64),
-                                        )
-                                        .gen::<f32>()
-                                            * 360.,
-                                        saturation: 1.,
-                                        lightness: 0.5,
-                                        alpha: 1.,
-                                    };
+                                    let color = player_color(id);
                                     connection.players.push(PlayerProp {
                                         user_id: id,
                                         color,
@@ -377,11 +573,33 @@ pub fn receive_msgs(
                                         last_update_time: None,
                                         score: 0,
                                         highest_score: 0,
+                                        team: None,
+                                        bot: None,
+                                        seat: None,
+                                        spectator: false,
+                                        ready: false,
+                                        profile: Profile::default(),
                                     });
                                     players_changed_ev.send(PlayersChanged {
                                         players: connection.players.clone(),
                                         self_player: connection.self_id,
                                     });
+                                    if !host.is_empty() {
+                                        // Let the newcomer see the food that's already out there.
+                                        for (_, food, kind, transform) in food.iter() {
+                                            if let Err(err) = connection.sender.send(
+                                                SendMessage::TransportMessage(
+                                                    TransportMessage::SpawnFood(
+                                                        food.0,
+                                                        transform.translation.truncate(),
+                                                        *kind,
+                                                    ),
+                                                ),
+                                            ) {
+                                                warn!("{err:?}")
+                                            }
+                                        }
+                                    }
                                 }
                                 RelayMessage::UserDisconnected(id, users) => {
                                     info!("User Disconnected {id}");
@@ -396,6 +614,16 @@ pub fn receive_msgs(
                                     }
                                     if let Some(player_index) = p_index {
                                         connection.players.remove(player_index);
+                                        // Whoever shared their keyboard left with them.
+                                        let removed =
+                                            sync_local_players(&mut connection.players, id, &[]);
+                                        for local_id in removed {
+                                            if let Some(snek) = snakes.iter().find(|p| {
+                                                p.1 == &SnakeTag::OtherPlayerSnake(local_id)
+                                            }) {
+                                                snake_killer.send(KillSnake { snake_id: snek.0 });
+                                            }
+                                        }
                                         players_changed_ev.send(PlayersChanged {
                                             players: connection.players.clone(),
                                             self_player: connection.self_id,
@@ -407,6 +635,17 @@ pub fn receive_msgs(
                                     let transport_msg =
                                         bincode::deserialize::<TransportMessage>(&msg);
                                     if let Ok(transport_msg) = transport_msg {
+                                        // The host speaks for its bots, everyone for the
+                                        // players sharing their keyboard.
+                                        let (user_id, transport_msg) = match transport_msg {
+                                            TransportMessage::OnBehalf(id, msg)
+                                                if is_bot(id)
+                                                    || is_local_player_of(id, user_id) =>
+                                            {
+                                                (id, *msg)
+                                            }
+                                            msg => (user_id, msg),
+                                        };
                                         match transport_msg {
                                             TransportMessage::Noop => {}
                                             TransportMessage::Ping(t) => {
@@ -419,13 +658,28 @@ pub fn receive_msgs(
                                                 }
                                             }
                                             TransportMessage::Pong(t) => {
-                                                info!("Ping {}", time.elapsed_seconds() - t);
+                                                let round_trip = time.elapsed_seconds() - t;
+                                                info!("Ping {}", round_trip);
+                                                clock.measured_round_trip(user_id, round_trip);
+                                            }
+                                            TransportMessage::ClockSync(host_time) => {
+                                                if host.is_empty() {
+                                                    clock.sync(user_id, host_time, &time);
+                                                }
                                             }
                                             TransportMessage::SnakeUpdate(
                                                 update_time,
                                                 snake_details,
                                             ) => {
-                                                if next_state.0 != Some(GameStates::GamePlay) {
+                                                // Snakes moving before the countdown is over
+                                                // shouldn't pull us in early.
+                                                let counting_down =
+                                                    current_match.started_at.is_some_and(
+                                                        |started_at| clock.now(&time) < started_at,
+                                                    );
+                                                if !counting_down
+                                                    && next_state.0 != Some(GameStates::GamePlay)
+                                                {
                                                     next_state.set(GameStates::GamePlay)
                                                 }
                                                 snake_update.send(SnakeUpdate {
@@ -451,16 +705,177 @@ pub fn receive_msgs(
                                             //         commands.entity(host).despawn();
                                             //     }
                                             // }
-                                            TransportMessage::StartGame(_start_time) => {
-                                                next_state.set(GameStates::GamePlay);
+                                            TransportMessage::StartGame(started_at, settings) => {
+                                                // The host repeats this, only a new start time is a new match.
+                                                if current_match.started_at != Some(started_at) {
+                                                    *current_match = CurrentMatch {
+                                                        settings,
+                                                        started_at: Some(started_at),
+                                                        results: None,
+                                                        left: None,
+                                                    };
+                                                }
+                                            }
+                                            TransportMessage::LobbySettings(settings) => {
+                                                if host.is_empty()
+                                                    && current_match.started_at.is_none()
+                                                    && current_match.settings != settings
+                                                {
+                                                    current_match.settings = settings;
+                                                }
+                                            }
+                                            TransportMessage::MatchOver(results) => {
+                                                current_match.results = Some(results);
+                                            }
+                                            TransportMessage::TeamAssignments(assignments) => {
+                                                let mut changed = false;
+                                                for player in connection.players.iter_mut() {
+                                                    if let Some((_, team)) = assignments
+                                                        .iter()
+                                                        .find(|(id, _)| *id == player.user_id)
+                                                    {
+                                                        changed |= player.team != *team;
+                                                        player.team = *team;
+                                                    }
+                                                }
+                                                if changed {
+                                                    players_changed_ev.send(PlayersChanged {
+                                                        players: connection.players.clone(),
+                                                        self_player: connection.self_id,
+                                                    });
+                                                }
                                             }
-                                            TransportMessage::SpawnFood(food_id, food_pos) => {
-                                                commands.spawn(spawn_food(
-                                                    food_id,
-                                                    config.cell_size,
-                                                    food_pos.x,
-                                                    food_pos.y,
-                                                ));
+                                            TransportMessage::ScoreChanged(score) => {
+                                                let player = connection
+                                                    .players
+                                                    .iter_mut()
+                                                    .find(|p| p.user_id == user_id);
+                                                if let Some(player) = player {
+                                                    player.score = score;
+                                                    player.highest_score =
+                                                        player.highest_score.max(score);
+                                                }
+                                            }
+                                            TransportMessage::FlagMoved(state) => {
+                                                commands.insert_resource(Flag(state));
+                                            }
+                                            TransportMessage::Bots(roster) => {
+                                                let before = connection.players.len();
+                                                let removed = sync_bot_roster(
+                                                    &mut connection.players,
+                                                    &roster,
+                                                );
+                                                let changed = !removed.is_empty()
+                                                    || before != connection.players.len();
+                                                for bot_id in removed {
+                                                    if let Some(snek) = snakes.iter().find(|p| {
+                                                        p.1 == &SnakeTag::OtherPlayerSnake(bot_id)
+                                                    }) {
+                                                        snake_killer
+                                                            .send(KillSnake { snake_id: snek.0 });
+                                                    }
+                                                }
+                                                if changed {
+                                                    players_changed_ev.send(PlayersChanged {
+                                                        players: connection.players.clone(),
+                                                        self_player: connection.self_id,
+                                                    });
+                                                }
+                                            }
+                                            TransportMessage::LocalPlayers(seats) => {
+                                                let before = connection.players.clone();
+                                                let removed = sync_local_players(
+                                                    &mut connection.players,
+                                                    user_id,
+                                                    &seats,
+                                                );
+                                                for local_id in removed {
+                                                    if let Some(snek) = snakes.iter().find(|p| {
+                                                        p.1 == &SnakeTag::OtherPlayerSnake(local_id)
+                                                    }) {
+                                                        snake_killer
+                                                            .send(KillSnake { snake_id: snek.0 });
+                                                    }
+                                                }
+                                                if connection.players != before {
+                                                    players_changed_ev.send(PlayersChanged {
+                                                        players: connection.players.clone(),
+                                                        self_player: connection.self_id,
+                                                    });
+                                                }
+                                            }
+                                            TransportMessage::OnBehalf(..) => {}
+                                            message @ (TransportMessage::Chat(..)
+                                            | TransportMessage::ChatAck(..)
+                                            | TransportMessage::Emote(_)
+                                            | TransportMessage::MapPing(_)) => {
+                                                peer_messages.send(PeerMessage { user_id, message })
+                                            }
+                                            TransportMessage::Profile(profile) => {
+                                                let player = connection
+                                                    .players
+                                                    .iter_mut()
+                                                    .find(|p| p.user_id == user_id);
+                                                if let Some(player) = player {
+                                                    let profile = profile.cleaned();
+                                                    if player.profile != profile {
+                                                        player.profile = profile;
+                                                        players_changed_ev.send(PlayersChanged {
+                                                            players: connection.players.clone(),
+                                                            self_player: connection.self_id,
+                                                        });
+                                                    }
+                                                }
+                                            }
+                                            TransportMessage::Spectating(spectating) => {
+                                                let player = connection
+                                                    .players
+                                                    .iter_mut()
+                                                    .find(|p| p.user_id == user_id);
+                                                if let Some(player) = player {
+                                                    if player.spectator != spectating {
+                                                        player.spectator = spectating;
+                                                        players_changed_ev.send(PlayersChanged {
+                                                            players: connection.players.clone(),
+                                                            self_player: connection.self_id,
+                                                        });
+                                                    }
+                                                }
+                                            }
+                                            TransportMessage::Ready(ready) => {
+                                                let player = connection
+                                                    .players
+                                                    .iter_mut()
+                                                    .find(|p| p.user_id == user_id);
+                                                if let Some(player) = player {
+                                                    if player.ready != ready {
+                                                        player.ready = ready;
+                                                        players_changed_ev.send(PlayersChanged {
+                                                            players: connection.players.clone(),
+                                                            self_player: connection.self_id,
+                                                        });
+                                                    }
+                                                }
+                                            }
+                                            TransportMessage::ReturnToLobby(started_at) => {
+                                                if current_match.leave(started_at) {
+                                                    next_state.set(GameStates::Lobby);
+                                                }
+                                            }
+                                            TransportMessage::SpawnFood(
+                                                food_id,
+                                                food_pos,
+                                                kind,
+                                            ) => {
+                                                if !food.iter().any(|f| f.1 .0 == food_id) {
+                                                    commands.spawn(spawn_food(
+                                                        food_id,
+                                                        kind,
+                                                        config.cell_size,
+                                                        food_pos.x,
+                                                        food_pos.y,
+                                                    ));
+                                                }
                                             }
                                             TransportMessage::DespawnFood(id) => {
                                                 let food = food.iter().find(|f| f.1 .0 == id);
@@ -468,6 +883,13 @@ pub fn receive_msgs(
                                                     commands.entity(food.0).despawn_recursive();
                                                 }
                                             }
+                                            TransportMessage::EffectsChanged(effects) => {
+                                                if let Some(snek) = snakes.iter().find(|p| {
+                                                    p.1 == &SnakeTag::OtherPlayerSnake(user_id)
+                                                }) {
+                                                    commands.entity(snek.0).insert(effects);
+                                                }
+                                            }
                                             TransportMessage::KillSnake => {
                                                 if let Some(snek) = snakes.iter().find(|p| {
                                                     p.1 == &SnakeTag::OtherPlayerSnake(user_id)
@@ -494,6 +916,10 @@ pub fn ping_send(
     mut ping_tick: ResMut<PingTimer>,
     time: Res<Time>,
     connection_handler: Res<ConnectionState>,
+    host: Query<&Host>,
+    clock: Res<GameClock>,
+    current_match: Res<CurrentMatch>,
+    state: Res<State<GameStates>>,
 ) {
     ping_tick.timer.tick(time.delta());
     if ping_tick.timer.finished() {
@@ -505,15 +931,86 @@ pub fn ping_send(
             {
                 warn!("{err:?}")
             }
+            if let Some(player) = connection.self_player() {
+                // Sent either way, so a peer that missed us switching back still catches up.
+                let messages = vec![
+                    TransportMessage::Profile(player.profile.clone()),
+                    TransportMessage::Spectating(player.spectator),
+                    TransportMessage::Ready(player.ready),
+                ];
+                for message in messages {
+                    if let Err(err) = connection
+                        .sender
+                        .send(SendMessage::TransportMessage(message))
+                    {
+                        warn!("{err:?}")
+                    }
+                }
+            }
+            if !host.is_empty() {
+                let mut messages = vec![
+                    TransportMessage::ClockSync(clock.now(&time)),
+                    TransportMessage::Bots(bot_roster(connection)),
+                    TransportMessage::TeamAssignments(team_assignments(connection)),
+                ];
+                // Repeated so peers that missed the start or the result still catch up.
+                match (current_match.started_at, &current_match.results) {
+                    (Some(started_at), None) => messages.push(TransportMessage::StartGame(
+                        started_at,
+                        current_match.settings,
+                    )),
+                    (Some(_), Some(results)) => {
+                        messages.push(TransportMessage::MatchOver(results.clone()))
+                    }
+                    (None, _) => {
+                        if state.get() == &GameStates::Lobby {
+                            // Tagged with the match it ends, a late copy can't end the next one.
+                            if let Some(left) = current_match.left {
+                                messages.push(TransportMessage::ReturnToLobby(left));
+                            }
+                            messages.push(TransportMessage::LobbySettings(current_match.settings));
+                        }
+                    }
+                }
+                for message in messages {
+                    if let Err(err) = connection
+                        .sender
+                        .send(SendMessage::TransportMessage(message))
+                    {
+                        warn!("{err:?}")
+                    }
+                }
+            }
         }
     }
 }
 
+/// Sends `message` to everyone in the room.
+pub fn send(connection: &ConnectionHandler, message: TransportMessage) {
+    if let Err(err) = connection
+        .sender
+        .send(SendMessage::TransportMessage(message))
+    {
+        warn!("{err:?}")
+    }
+}
+
+/// Sends `message` as `user_id`: ourselves, or a bot or local player this machine speaks for.
+pub fn send_for(connection: &ConnectionHandler, user_id: u32, message: TransportMessage) {
+    let message = if Some(user_id) == connection.self_id {
+        message
+    } else {
+        TransportMessage::OnBehalf(user_id, Box::new(message))
+    };
+    send(connection, message);
+}
+
+/// Tells everyone where the snakes of the players at this machine are.
 pub fn send_snake_send(
     transforms: Query<&Transform, Or<(With<SnakeTag>, With<CellTag>)>>,
-    moves: Query<&Moves>,
+    moves: Query<(&Moves, &ActiveEffects, &SnakeSpeed)>,
     moveid_direc: Query<(&Direction, &MoveId, &CellTag)>,
-    snake: Query<(Entity, &SnakeTag)>,
+    snake: Query<(Entity, &SnakeTag), With<Player>>,
     snake_cells: Query<(&Parent, Entity), With<CellTag>>,
     connection_handler: Res<ConnectionState>,
     mut spawner_tick: ResMut<SnakeSyncTimer>,
@@ -523,22 +1020,39 @@ pub fn send_snake_send(
     if !spawner_tick.timer.finished() {
         return;
     }
-    let Some((self_snake, _snake_tag)) =
-        snake.iter().find(|val| val.1 == &SnakeTag::SelfPlayerSnake)
-    else {
-        return;
-    };
-    let Ok(snake_tranform) = transforms.get(self_snake).cloned() else {
-        return;
-    };
-    let Ok(moves) = moves.get(self_snake) else {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
         return;
     };
+    for (snake, snake_tag) in snake.iter() {
+        let (Some(user_id), Some(snake_details)) = (
+            connection.snake_owner(snake_tag),
+            snake_details(snake, &transforms, &moves, &moveid_direc, &snake_cells),
+        ) else {
+            continue;
+        };
+        send_for(
+            connection,
+            user_id,
+            TransportMessage::SnakeUpdate(time.elapsed_seconds(), snake_details),
+        );
+    }
+}
+
+/// Everything peers need to mirror `snake`.
+pub fn snake_details(
+    snake: Entity,
+    transforms: &Query<&Transform, Or<(With<SnakeTag>, With<CellTag>)>>,
+    moves: &Query<(&Moves, &ActiveEffects, &SnakeSpeed)>,
+    moveid_direc: &Query<(&Direction, &MoveId, &CellTag)>,
+    snake_cells: &Query<(&Parent, Entity), With<CellTag>>,
+) -> Option<SnakeDetails> {
+    let snake_tranform = transforms.get(snake).cloned().ok()?;
+    let (moves, effects, speed) = moves.get(snake).ok()?;
     let moves = moves.clone();
 
     let snake_cells = snake_cells
         .iter()
-        .filter(|cell| cell.0.get() == self_snake)
+        .filter(|cell| cell.0.get() == snake)
         .map(|(_par, cell)| {
             let transform = transforms.get(cell).unwrap();
             let (dir, move_id, tag) = moveid_direc.get(cell).unwrap();
@@ -550,21 +1064,13 @@ pub fn send_snake_send(
             }
         })
         .collect();
-    let snake_details = SnakeDetails {
+    Some(SnakeDetails {
         transform: snake_tranform,
         cells: snake_cells,
         moves,
-    };
-    match connection_handler.as_ref() {
-        ConnectionState::NotConnected => {}
-        ConnectionState::Connected(connection) => {
-            if let Err(err) = connection.sender.send(SendMessage::TransportMessage(
-                TransportMessage::SnakeUpdate(time.elapsed_seconds(), snake_details),
-            )) {
-                warn!("{err:?}")
-            }
-        }
-    }
+        effects: effects.clone(),
+        speed: *speed,
+    })
 }
 
 pub fn update_snake(
@@ -572,6 +1078,7 @@ pub fn update_snake(
     mut commands: Commands,
     mut snake: Query<(Entity, &SnakeTag)>,
     cells: Query<(Entity, &CellTag)>,
+    children: Query<&Children>,
     config: Res<GameConfig>,
     mut moves: Query<&mut Moves>,
     mut move_id: Query<&mut MoveId>,
@@ -609,6 +1116,23 @@ pub fn update_snake(
         if let Some(snake) = snake {
             *transmform.get_mut(snake.0).unwrap() = event.snake_details.transform;
             *moves.get_mut(snake.0).unwrap() = event.snake_details.moves.clone();
+            commands.entity(snake.0).insert((
+                event.snake_details.effects.clone(),
+                event.snake_details.speed,
+            ));
+            // Cells the owner no longer has, e.g. after eating shrinking food.
+            for child in children.get(snake.0).into_iter().flatten() {
+                if let Ok((cell_entity, cell_tag)) = cells.get(*child) {
+                    if !event
+                        .snake_details
+                        .cells
+                        .iter()
+                        .any(|cell| &cell.cell_tag == cell_tag)
+                    {
+                        commands.entity(cell_entity).despawn_recursive();
+                    }
+                }
+            }
             for cell in event.snake_details.cells.iter() {
                 let cell_entity = cells.iter().find(|p| p.1 == &cell.cell_tag);
                 let compensation_time =
@@ -625,8 +1149,9 @@ pub fn update_snake(
                     }
                     .clamp(0., f32::INFINITY);
                 let direction_vec3: Vec3 = cell.direction.clone().into();
-                let compensation_transform: Vec3 =
-                    compensation_time * config.speed * direction_vec3;
+                let speed =
+                    event.snake_details.speed.0 * event.snake_details.effects.speed_multiplier();
+                let compensation_transform: Vec3 = compensation_time * speed * direction_vec3;
                 if let Some(cell_entity) = cell_entity {
                     *transmform.get_mut(cell_entity.0).unwrap() = cell
                         .transform
@@ -642,7 +1167,7 @@ pub fn update_snake(
                             direction: cell.direction.clone(),
                             sprite: SpriteBundle {
                                 sprite: Sprite {
-                                    color: player.color,
+                                    color: player.snake_color(),
                                     custom_size: Some(Vec2::new(cell_size.0, cell_size.1)),
                                     ..default()
                                 },
@@ -665,6 +1190,8 @@ pub fn update_snake(
 
                     lastmove: LastMoveId(0),
                     moves: event.snake_details.moves.clone(),
+                    effects: event.snake_details.effects.clone(),
+                    speed: event.snake_details.speed,
                 },))
                 .with_children(|parent| {
                     for cell in event.snake_details.cells.iter() {
@@ -675,7 +1202,7 @@ pub fn update_snake(
                             direction: cell.direction.clone(),
                             sprite: SpriteBundle {
                                 sprite: Sprite {
-                                    color: player.color,
+                                    color: player.snake_color(),
                                     custom_size: Some(Vec2::new(cell_size.0, cell_size.1)),
                                     ..default()
                                 },
@@ -0,0 +1,420 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::Arena,
+    bots::is_bot,
+    game_mode::{CurrentMatch, ModeObjective},
+    networking::{send_for, ConnectionHandler, ConnectionState, SendMessage, TransportMessage},
+    split_screen::is_local_player_of,
+    CellTag, Host, SnakeTag, Tail,
+};
+
+/// Anything placed in the arena for the current mode's objective.
+#[derive(Component)]
+pub struct Objective;
+
+/// The square zone in the middle of the arena for king of the hill.
+#[derive(Component)]
+pub struct Hill;
+
+const HILL_HALF_SIZE: f32 = 120.0;
+
+/// Seconds alone on the hill for each point.
+const HILL_SECONDS_PER_POINT: f32 = 1.0;
+
+/// Points for bringing the flag home.
+const CAPTURE_POINTS: u32 = 10;
+
+const FLAG_PICKUP_RADIUS: f32 = 25.0;
+
+const BASE_RADIUS: f32 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FlagState {
+    AtRest(Vec2),
+    CarriedBy(u32),
+}
+
+/// Where the flag is in capture the flag. Whoever changes it tells everyone else: the host
+/// hands it out, the carrier brings it home or drops it.
+#[derive(Resource)]
+pub struct Flag(pub FlagState);
+
+#[derive(Component)]
+pub struct FlagMarker;
+
+/// Drop-off point for the flag, numbered like [`home_base`].
+#[derive(Component)]
+pub struct HomeBase(usize);
+
+/// Hands `user_id`, a player at this machine or one of our bots, `points` and tells everyone
+/// their new score.
+pub fn add_points(connection: &mut ConnectionHandler, user_id: u32, points: u32) {
+    let Some(player) = connection.players.iter_mut().find(|p| p.user_id == user_id) else {
+        return;
+    };
+    player.score += points;
+    player.highest_score = player.highest_score.max(player.score);
+    let score = player.score;
+    send_for(connection, user_id, TransportMessage::ScoreChanged(score));
+}
+
+/// Whether `user_id` is steered at this machine: our own snake, one sharing our keyboard, or
+/// on the host one of its bots.
+fn steered_here(user_id: u32, self_id: u32, is_host: bool) -> bool {
+    user_id == self_id || is_local_player_of(user_id, self_id) || (is_host && is_bot(user_id))
+}
+
+/// Whether `head` is on the hill at `hill`, the same square the sprite shows.
+fn on_hill(hill: Vec2, head: Vec2) -> bool {
+    let offset = (head - hill).abs();
+    offset.x < HILL_HALF_SIZE && offset.y < HILL_HALF_SIZE
+}
+
+/// Where the head of every snake is, with the user it belongs to.
+fn snake_heads(
+    self_id: u32,
+    snakes: &Query<(&SnakeTag, &Children)>,
+    cells: &Query<&GlobalTransform, With<CellTag>>,
+) -> Vec<(u32, Vec2)> {
+    snakes
+        .iter()
+        .filter_map(|(tag, children)| {
+            let user_id = match tag {
+                SnakeTag::SelfPlayerSnake => self_id,
+                SnakeTag::OtherPlayerSnake(id) => *id,
+            };
+            let head = children.iter().find_map(|cell| cells.get(*cell).ok())?;
+            Some((user_id, head.translation().truncate()))
+        })
+        .collect()
+}
+
+/// Objective scores and the flag start over with every match.
+pub fn reset_objectives(mut commands: Commands, mut connection_handler: ResMut<ConnectionState>) {
+    commands.remove_resource::<Flag>();
+    if let ConnectionState::Connected(connection) = connection_handler.as_mut() {
+        for player in connection.players.iter_mut() {
+            player.score = 0;
+        }
+    }
+}
+
+/// Puts the hill or the flag in the arena once the mode is known.
+pub fn spawn_objectives(
+    mut commands: Commands,
+    current_match: Res<CurrentMatch>,
+    objectives: Query<Entity, With<Objective>>,
+    hill: Query<&Hill>,
+    flag: Query<&FlagMarker>,
+    flag_state: Option<Res<Flag>>,
+) {
+    match current_match.settings.mode.rules().objective() {
+        Some(ModeObjective::Hill) if hill.is_empty() => {
+            commands.spawn((
+                Objective,
+                Hill,
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(1.0, 1.0, 1.0, 0.25),
+                        custom_size: Some(Vec2::splat(HILL_HALF_SIZE * 2.0)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, 0.0, 0.3),
+                    ..default()
+                },
+            ));
+        }
+        Some(ModeObjective::Flag) if flag.is_empty() => {
+            // A peer may already have told us where the flag is.
+            if flag_state.is_none() {
+                commands.insert_resource(Flag(FlagState::AtRest(Vec2::ZERO)));
+            }
+            commands.spawn((
+                Objective,
+                FlagMarker,
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::WHITE,
+                        custom_size: Some(Vec2::new(14.0, 22.0)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, 0.0, 1.5),
+                    ..default()
+                },
+            ));
+        }
+        Some(_) => {}
+        None => {
+            for entity in objectives.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Awards points to a snake steered at this machine, the host's bots included, while its
+/// head is alone on the hill. Any other snake's head on the hill contests it.
+pub fn score_hill(
+    time: Res<Time>,
+    mut hill: Query<(&Transform, &mut Sprite), With<Hill>>,
+    snakes: Query<(&SnakeTag, &Children)>,
+    cells: Query<&GlobalTransform, With<CellTag>>,
+    host: Query<&Host>,
+    mut connection_handler: ResMut<ConnectionState>,
+    mut held_for: Local<f32>,
+) {
+    let Ok((hill_transform, mut sprite)) = hill.get_single_mut() else {
+        return;
+    };
+    let ConnectionState::Connected(connection) = connection_handler.as_mut() else {
+        return;
+    };
+    let Some(self_id) = connection.self_id else {
+        return;
+    };
+    let is_host = !host.is_empty();
+    let hill_position = hill_transform.translation.truncate();
+    let on_hill: Vec<u32> = snake_heads(self_id, &snakes, &cells)
+        .into_iter()
+        .filter(|(_, head)| on_hill(hill_position, *head))
+        .map(|(user_id, _)| user_id)
+        .collect();
+    let ours = |user_id: &u32| steered_here(*user_id, self_id, is_host);
+    let holding = on_hill.iter().any(ours);
+    let contested = on_hill.len() > 1 || on_hill.iter().any(|user_id| !ours(user_id));
+
+    sprite.color = match (holding, contested) {
+        (true, false) => Color::rgba(1.0, 0.85, 0.2, 0.35),
+        (_, true) => Color::rgba(1.0, 0.3, 0.2, 0.3),
+        (false, false) => Color::rgba(1.0, 1.0, 1.0, 0.25),
+    };
+    if !holding || contested {
+        return;
+    }
+    *held_for += time.delta_seconds();
+    if *held_for >= HILL_SECONDS_PER_POINT {
+        *held_for -= HILL_SECONDS_PER_POINT;
+        add_points(connection, on_hill[0], 1);
+    }
+}
+
+/// Index of the base `user_id` brings the flag to, and how many bases there are.
+///
+/// Teams share a base; without teams everyone has their own, ordered by user id so every
+/// peer numbers them the same way.
+fn home_base(connection: &ConnectionHandler, user_id: u32) -> Option<(usize, usize)> {
+    let player = connection.playing().find(|p| p.user_id == user_id)?;
+    if let Some(team) = player.team {
+        let teams = connection
+            .players
+            .iter()
+            .filter_map(|p| p.team)
+            .max()
+            .unwrap_or(team) as usize
+            + 1;
+        return Some((team as usize, teams));
+    }
+    let mut ids: Vec<u32> = connection.playing().map(|p| p.user_id).collect();
+    ids.sort();
+    let index = ids.iter().position(|id| *id == user_id)?;
+    Some((index, ids.len()))
+}
+
+fn base_position(index: usize, count: usize, arena: &Arena) -> Vec2 {
+    let angle =
+        std::f32::consts::TAU * index as f32 / count.max(1) as f32 + std::f32::consts::FRAC_PI_4;
+    Vec2::from_angle(angle) * arena.shape.inner_radius() * 0.7
+}
+
+/// Moves the flag along. Only the host picks it up for a snake, so two snakes reaching it
+/// at once can't both take it; whoever steers the carrier, the host for its bots, brings it
+/// home or drops it.
+pub fn update_flag(
+    flag: Option<ResMut<Flag>>,
+    flag_marker: Query<&Transform, With<FlagMarker>>,
+    snakes: Query<(&SnakeTag, &Children)>,
+    cells: Query<&GlobalTransform, With<CellTag>>,
+    host: Query<&Host>,
+    arena: Res<Arena>,
+    mut connection_handler: ResMut<ConnectionState>,
+) {
+    let (Some(mut flag), Ok(flag_transform)) = (flag, flag_marker.get_single()) else {
+        return;
+    };
+    let ConnectionState::Connected(connection) = connection_handler.as_mut() else {
+        return;
+    };
+    let Some(self_id) = connection.self_id else {
+        return;
+    };
+    let is_host = !host.is_empty();
+    let flag_position = flag_transform.translation.truncate();
+    let heads = snake_heads(self_id, &snakes, &cells);
+    let head_of = |user_id: u32| {
+        heads
+            .iter()
+            .find(|(id, _)| *id == user_id)
+            .map(|(_, head)| *head)
+    };
+
+    let new_state = match flag.0 {
+        FlagState::AtRest(position) if is_host => {
+            let Some((taker, _)) = heads
+                .iter()
+                .find(|(_, head)| head.distance(position) < FLAG_PICKUP_RADIUS)
+            else {
+                return;
+            };
+            FlagState::CarriedBy(*taker)
+        }
+        FlagState::CarriedBy(carrier) if steered_here(carrier, self_id, is_host) => {
+            match head_of(carrier) {
+                Some(head) => {
+                    let Some((index, count)) = home_base(connection, carrier) else {
+                        return;
+                    };
+                    if head.distance(base_position(index, count, &arena)) >= BASE_RADIUS {
+                        return;
+                    }
+                    add_points(connection, carrier, CAPTURE_POINTS);
+                    FlagState::AtRest(Vec2::ZERO)
+                }
+                // The carrier died with the flag in tow.
+                None => FlagState::AtRest(flag_position),
+            }
+        }
+        // The carrier left the room.
+        FlagState::CarriedBy(carrier)
+            if is_host && !connection.players.iter().any(|p| p.user_id == carrier) =>
+        {
+            FlagState::AtRest(flag_position)
+        }
+        _ => return,
+    };
+    flag.0 = new_state;
+    if let Err(err) =
+        connection
+            .sender
+            .send(SendMessage::TransportMessage(TransportMessage::FlagMoved(
+                new_state,
+            )))
+    {
+        warn!("{err:?}")
+    }
+}
+
+/// Draws the flag where it lies, or trailing behind the tail of whoever carries it.
+pub fn place_flag(
+    flag: Option<Res<Flag>>,
+    mut flag_marker: Query<&mut Transform, With<FlagMarker>>,
+    snakes: Query<(Entity, &SnakeTag)>,
+    tails: Query<(&Parent, &GlobalTransform), With<Tail>>,
+    connection_handler: Res<ConnectionState>,
+) {
+    let (Some(flag), Ok(mut transform)) = (flag, flag_marker.get_single_mut()) else {
+        return;
+    };
+    let position = match flag.0 {
+        FlagState::AtRest(position) => Some(position),
+        FlagState::CarriedBy(carrier) => {
+            let self_id = match connection_handler.as_ref() {
+                ConnectionState::Connected(connection) => connection.self_id,
+                ConnectionState::NotConnected => None,
+            };
+            let tag = if Some(carrier) == self_id {
+                SnakeTag::SelfPlayerSnake
+            } else {
+                SnakeTag::OtherPlayerSnake(carrier)
+            };
+            snakes
+                .iter()
+                .find(|(_, snake_tag)| **snake_tag == tag)
+                .and_then(|(snake, _)| tails.iter().find(|(parent, _)| parent.get() == snake))
+                .map(|(_, tail)| tail.translation().truncate())
+        }
+    };
+    if let Some(position) = position {
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+/// Keeps one base marker per team, or per player without teams.
+pub fn display_home_bases(
+    mut commands: Commands,
+    current_match: Res<CurrentMatch>,
+    bases: Query<Entity, With<HomeBase>>,
+    arena: Res<Arena>,
+    connection_handler: Res<ConnectionState>,
+    mut shown: Local<Vec<(Vec2, Color)>>,
+) {
+    let mut wanted: Vec<(Vec2, Color)> = vec![];
+    if let (Some(ModeObjective::Flag), ConnectionState::Connected(connection)) = (
+        current_match.settings.mode.rules().objective(),
+        connection_handler.as_ref(),
+    ) {
+        for player in connection.players.iter() {
+            if let Some((index, count)) = home_base(connection, player.user_id) {
+                let position = base_position(index, count, &arena);
+                let color = player.snake_color();
+                if !wanted.iter().any(|(p, _)| *p == position) {
+                    wanted.push((position, color));
+                }
+            }
+        }
+    }
+    if *shown == wanted && bases.iter().count() == wanted.len() {
+        return;
+    }
+    for base in bases.iter() {
+        commands.entity(base).despawn_recursive();
+    }
+    for (index, (position, color)) in wanted.iter().enumerate() {
+        commands.spawn((
+            Objective,
+            HomeBase(index),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: color.with_a(0.35),
+                    custom_size: Some(Vec2::splat(BASE_RADIUS * 2.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(0.3)),
+                ..default()
+            },
+        ));
+    }
+    *shown = wanted;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bots::BOT_ID_BASE,
+        split_screen::{local_player_id, KeySet},
+    };
+
+    #[test]
+    fn hill_is_the_square_it_looks_like() {
+        let hill = Vec2::new(10.0, -20.0);
+        assert!(on_hill(hill, hill));
+        // A corner is outside the circle that fits in the square, but still on the hill.
+        let corner = hill + Vec2::splat(HILL_HALF_SIZE - 1.0);
+        assert!(corner.distance(hill) > HILL_HALF_SIZE);
+        assert!(on_hill(hill, corner));
+        assert!(!on_hill(hill, hill + Vec2::new(HILL_HALF_SIZE + 1.0, 0.0)));
+    }
+
+    #[test]
+    fn only_the_host_scores_for_bots() {
+        let self_id = 7;
+        assert!(steered_here(self_id, self_id, false));
+        assert!(!steered_here(8, self_id, true));
+        assert!(steered_here(BOT_ID_BASE, self_id, true));
+        assert!(!steered_here(BOT_ID_BASE, self_id, false));
+        let seat = local_player_id(self_id, KeySet::ALL[0]);
+        assert!(steered_here(seat, self_id, false));
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::{Collider, Sensor};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    arena::Arena,
+    networking::{ConnectionState, GameClock},
+};
+
+/// Something in the arena that kills a snake whose head runs into it.
+#[derive(Component)]
+pub struct Obstacle;
+
+/// Spins an obstacle around its centre, driven by the shared [`GameClock`].
+#[derive(Component)]
+pub struct RotatingBar {
+    angular_speed: f32,
+    phase: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObstacleShape {
+    Rock {
+        size: f32,
+    },
+    Wall {
+        half_size: Vec2,
+    },
+    RotatingBar {
+        half_length: f32,
+        angular_speed: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObstacleLayout {
+    pub shape: ObstacleShape,
+    pub position: Vec2,
+    pub rotation: f32,
+}
+
+const ROCKS: usize = 10;
+const WALLS: usize = 6;
+const ROTATING_BARS: usize = 3;
+
+/// Obstacles are kept between these distances from the centre, leaving the middle open.
+const MIN_RADIUS: f32 = 150.0;
+const MAX_RADIUS: f32 = 850.0;
+
+/// Lays out the arena's obstacles. Every peer gets the same layout for the same seed.
+pub fn generate_obstacles(seed: u64, arena: &Arena) -> Vec<ObstacleLayout> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut layout: Vec<ObstacleLayout> = vec![];
+    let mut shapes = vec![];
+    for _ in 0..ROCKS {
+        shapes.push(ObstacleShape::Rock {
+            size: rng.gen_range(30.0..70.0),
+        });
+    }
+    for _ in 0..WALLS {
+        shapes.push(ObstacleShape::Wall {
+            half_size: Vec2::new(rng.gen_range(60.0..140.0), 10.0),
+        });
+    }
+    for _ in 0..ROTATING_BARS {
+        let direction = if rng.gen() { 1.0 } else { -1.0 };
+        shapes.push(ObstacleShape::RotatingBar {
+            half_length: rng.gen_range(90.0..150.0),
+            angular_speed: rng.gen_range(0.4..0.8) * direction,
+        });
+    }
+
+    for shape in shapes {
+        // Try a few spots, skipping ones that crowd an obstacle that's already placed.
+        for _ in 0..20 {
+            let rad = rng.gen_range(MIN_RADIUS..MAX_RADIUS);
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let position = Vec2::from_angle(angle) * rad;
+            let crowded = !arena.contains(position, shape.reach())
+                || layout
+                    .iter()
+                    .any(|other| other.position.distance(position) < 2.0 * shape.reach());
+            if !crowded {
+                let rotation = match shape {
+                    ObstacleShape::Wall { .. } => {
+                        if rng.gen() {
+                            0.0
+                        } else {
+                            std::f32::consts::FRAC_PI_2
+                        }
+                    }
+                    _ => rng.gen_range(0.0..std::f32::consts::TAU),
+                };
+                layout.push(ObstacleLayout {
+                    shape,
+                    position,
+                    rotation,
+                });
+                break;
+            }
+        }
+    }
+    layout
+}
+
+impl ObstacleShape {
+    /// Rough radius of the area the obstacle can touch.
+    pub fn reach(&self) -> f32 {
+        match *self {
+            ObstacleShape::Rock { size } => size * std::f32::consts::FRAC_1_SQRT_2,
+            ObstacleShape::Wall { half_size } => half_size.length(),
+            ObstacleShape::RotatingBar { half_length, .. } => half_length,
+        }
+    }
+}
+
+/// Room ids are shared by everyone in the room, so they double as the seed for the layout.
+pub fn room_seed(room_id: &str) -> u64 {
+    room_id.parse().unwrap_or_else(|_| {
+        room_id.bytes().fold(0u64, |hash, byte| {
+            hash.wrapping_mul(31).wrapping_add(byte as u64)
+        })
+    })
+}
+
+pub fn spawn_obstacles(
+    mut commands: Commands,
+    obstacles: Query<&Obstacle>,
+    arena: Res<Arena>,
+    connection_handler: Res<ConnectionState>,
+) {
+    if !obstacles.is_empty() {
+        return;
+    }
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    for obstacle in generate_obstacles(room_seed(&connection.room_id), &arena) {
+        let (size, collider, color) = match obstacle.shape {
+            ObstacleShape::Rock { size } => (
+                Vec2::splat(size),
+                Collider::cuboid(size / 2.0, size / 2.0),
+                Color::rgb(0.42, 0.38, 0.35),
+            ),
+            ObstacleShape::Wall { half_size } => (
+                half_size * 2.0,
+                Collider::cuboid(half_size.x, half_size.y),
+                Color::rgb(0.55, 0.27, 0.07),
+            ),
+            ObstacleShape::RotatingBar { half_length, .. } => (
+                Vec2::new(half_length * 2.0, 16.0),
+                Collider::cuboid(half_length, 8.0),
+                Color::rgb(0.75, 0.1, 0.1),
+            ),
+        };
+        let mut entity = commands.spawn((
+            Obstacle,
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_translation(obstacle.position.extend(0.5))
+                    .with_rotation(Quat::from_rotation_z(obstacle.rotation)),
+                ..default()
+            },
+            collider,
+            Sensor,
+        ));
+        if let ObstacleShape::RotatingBar { angular_speed, .. } = obstacle.shape {
+            entity.insert(RotatingBar {
+                angular_speed,
+                phase: obstacle.rotation,
+            });
+        }
+    }
+}
+
+pub fn rotate_obstacles(
+    time: Res<Time>,
+    clock: Res<GameClock>,
+    mut bars: Query<(&mut Transform, &RotatingBar)>,
+) {
+    let now = clock.now(&time);
+    for (mut transform, bar) in bars.iter_mut() {
+        transform.rotation = Quat::from_rotation_z(bar.phase + bar.angular_speed * now);
+    }
+}
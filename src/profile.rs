@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    networking::{ConnectionState, PlayersChanged, SendMessage, TransportMessage},
+    skins::{Skin, PALETTE},
+    CellTag, SnakeTag,
+};
+
+/// Longest name anyone gets to show, in characters.
+pub const MAX_NAME_LENGTH: usize = 16;
+
+const NAME_KEY: &str = "name";
+const COLOR_KEY: &str = "color";
+const SKIN_KEY: &str = "skin";
+
+/// How the player presents themselves to the room. Kept on this machine between sessions.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    /// Index into [`PALETTE`] of the colour the player would like, if they picked one.
+    pub color: Option<u8>,
+    pub skin: Skin,
+}
+
+impl Profile {
+    pub fn load() -> Self {
+        Profile {
+            name: load_setting(NAME_KEY)
+                .map(|name| clean_name(&name))
+                .unwrap_or_default(),
+            color: load_setting(COLOR_KEY).and_then(|color| color.parse().ok()),
+            skin: load_setting(SKIN_KEY)
+                .and_then(|skin| Skin::ALL.into_iter().find(|s| s.label() == skin))
+                .unwrap_or_default(),
+        }
+        .cleaned()
+    }
+
+    pub fn save(&self) {
+        save_setting(NAME_KEY, &self.name);
+        save_setting(
+            COLOR_KEY,
+            &self
+                .color
+                .map(|color| color.to_string())
+                .unwrap_or_default(),
+        );
+        save_setting(SKIN_KEY, self.skin.label());
+    }
+
+    /// The same profile with anything a peer shouldn't be able to send cut out.
+    pub fn cleaned(&self) -> Self {
+        Profile {
+            name: clean_name(&self.name),
+            color: self.color.filter(|color| (*color as usize) < PALETTE.len()),
+            skin: self.skin,
+        }
+    }
+}
+
+/// Trims whitespace and control characters and caps the length.
+pub fn clean_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_LENGTH)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(not(target_family = "wasm"))]
+const SETTINGS_FILE: &str = "settings.txt";
+
+/// A value saved with [`save_setting`], from `settings.txt` next to the game.
+#[cfg(not(target_family = "wasm"))]
+fn load_setting(key: &str) -> Option<String> {
+    let settings = std::fs::read_to_string(SETTINGS_FILE).ok()?;
+    settings.lines().find_map(|line| {
+        let (line_key, value) = line.split_once('=')?;
+        (line_key == key).then(|| value.to_string())
+    })
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn save_setting(key: &str, value: &str) {
+    let settings = std::fs::read_to_string(SETTINGS_FILE).unwrap_or_default();
+    let mut lines: Vec<String> = settings
+        .lines()
+        .filter(|line| line.split_once('=').map(|(k, _)| k) != Some(key))
+        .map(str::to_string)
+        .collect();
+    lines.push(format!("{key}={value}"));
+    if let Err(err) = std::fs::write(SETTINGS_FILE, lines.join("\n") + "\n") {
+        warn!("Couldn't save settings: {err:?}")
+    }
+}
+
+/// A value saved with [`save_setting`], from the browser's local storage.
+#[cfg(target_family = "wasm")]
+fn load_setting(key: &str) -> Option<String> {
+    let storage = web_sys::window()?.local_storage().ok()??;
+    storage.get_item(&format!("snek.{key}")).ok()?
+}
+
+#[cfg(target_family = "wasm")]
+fn save_setting(key: &str, value: &str) {
+    let Some(Ok(Some(storage))) = web_sys::window().map(|window| window.local_storage()) else {
+        return;
+    };
+    if let Err(err) = storage.set_item(&format!("snek.{key}"), value) {
+        warn!("Couldn't save settings: {err:?}")
+    }
+}
+
+/// Puts our [`Profile`] into our own [`PlayerProp`] and tells the room when it changes.
+///
+/// [`PlayerProp`]: crate::networking::PlayerProp
+pub fn sync_profile(
+    profile: Res<Profile>,
+    mut connection_handler: ResMut<ConnectionState>,
+    mut players_changed: EventWriter<PlayersChanged>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_mut() else {
+        return;
+    };
+    let Some(self_id) = connection.self_id else {
+        return;
+    };
+    let Some(player) = connection.players.iter_mut().find(|p| p.user_id == self_id) else {
+        return;
+    };
+    if player.profile == *profile {
+        return;
+    }
+    player.profile = profile.clone();
+    if let Err(err) =
+        connection
+            .sender
+            .send(SendMessage::TransportMessage(TransportMessage::Profile(
+                profile.clone(),
+            )))
+    {
+        warn!("{err:?}")
+    }
+    players_changed.send(PlayersChanged {
+        players: connection.players.clone(),
+        self_player: connection.self_id,
+    });
+}
+
+/// Name floating above the head of a snake.
+#[derive(Component)]
+pub struct NameTag(Entity);
+
+/// Keeps a name tag over every snake's head, and drops the tags of snakes that are gone.
+pub fn display_name_tags(
+    mut commands: Commands,
+    snakes: Query<(Entity, &SnakeTag, &Children)>,
+    heads: Query<&GlobalTransform, With<CellTag>>,
+    mut tags: Query<(Entity, &NameTag, &mut Transform, &mut Text)>,
+    connection_handler: Res<ConnectionState>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    for (entity, tag, ..) in tags.iter() {
+        if !snakes.contains(tag.0) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for (snake, snake_tag, cells) in snakes.iter() {
+        let user_id = match snake_tag {
+            SnakeTag::SelfPlayerSnake => connection.self_id,
+            SnakeTag::OtherPlayerSnake(id) => Some(*id),
+        };
+        let Some(player) =
+            user_id.and_then(|id| connection.players.iter().find(|p| p.user_id == id))
+        else {
+            continue;
+        };
+        // The head is the first cell.
+        let Some(head) = cells.iter().find_map(|cell| heads.get(*cell).ok()) else {
+            continue;
+        };
+        let translation = (head.translation().truncate() + Vec2::new(0.0, 30.0)).extend(10.0);
+        let name = player.name();
+        match tags.iter_mut().find(|(_, tag, ..)| tag.0 == snake) {
+            Some((_, _, mut transform, mut text)) => {
+                transform.translation = translation;
+                if let Some(section) = text.sections.first_mut() {
+                    if section.value != name {
+                        section.value = name;
+                    }
+                }
+            }
+            None => {
+                commands.spawn((
+                    NameTag(snake),
+                    Text2dBundle {
+                        text: Text::from_section(
+                            name,
+                            TextStyle {
+                                font_size: 18.0,
+                                color: player.snake_color(),
+                                ..default()
+                            },
+                        ),
+                        transform: Transform::from_translation(translation),
+                        ..default()
+                    },
+                ));
+            }
+        }
+    }
+}
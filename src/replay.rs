@@ -0,0 +1,457 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use flume::{Receiver, Sender};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    food::Food,
+    game_mode::{CurrentMatch, MatchSettings},
+    networking::{
+        ConnectionHandler, ConnectionState, PlayerProp, PointInTime, ReceiveMessage, RelayMessage,
+        SendMessage,
+    },
+    terrain::FreeCamera,
+    GameStates, Host, MainCamera, SnakeTag,
+};
+
+/// Folder matches are recorded into.
+const REPLAY_DIR: &str = "replays";
+
+/// Recordings kept in [`REPLAY_DIR`] unless `--keep-replays` says otherwise.
+const DEFAULT_KEEP: usize = 20;
+
+/// Seconds the left and right arrows skip.
+const SEEK_SECONDS: f32 = 5.0;
+
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 8.0;
+
+/// Pixels a second the free camera pans at, before zooming.
+const PAN_SPEED: f32 = 600.0;
+
+#[derive(Serialize, Deserialize)]
+enum Recorded {
+    /// A datagram from the relay, as it arrived.
+    Received(Vec<u8>),
+    /// One of our own messages, which carry our inputs.
+    Sent(Vec<u8>),
+}
+
+/// Everything needed to play a match back: the room as it was when the match started, then
+/// all traffic in and out with the seconds since.
+#[derive(Serialize, Deserialize)]
+struct Recording {
+    room_id: String,
+    self_id: Option<u32>,
+    players: Vec<PlayerProp>,
+    settings: MatchSettings,
+    started_at: Option<PointInTime>,
+    entries: Vec<(f32, Recorded)>,
+}
+
+impl Recording {
+    fn length(&self) -> f32 {
+        self.entries.last().map_or(0.0, |(at, _)| *at)
+    }
+
+    /// The entry as `receive_msgs` gets it. Our own messages come back as if someone else
+    /// with our id sent them, so our snake plays back like everyone else's.
+    fn datagram(&self, entry: &Recorded) -> Option<Vec<u8>> {
+        match entry {
+            Recorded::Received(data) => Some(data.clone()),
+            Recorded::Sent(msg) => {
+                bincode::serialize(&RelayMessage::UserMessage(self.self_id?, msg.clone())).ok()
+            }
+        }
+    }
+}
+
+/// Sits between the game and the relay while a match is on, writing down what passes.
+#[derive(Resource)]
+pub struct Recorder {
+    recording: Recording,
+    started: f32,
+    game_sender: Sender<ReceiveMessage>,
+    game_receiver: Receiver<SendMessage>,
+    transport_sender: Sender<SendMessage>,
+    transport_receiver: Receiver<ReceiveMessage>,
+}
+
+/// Recording picked on the command line with `--replay <file>`.
+#[derive(Resource)]
+pub struct ReplayFile(PathBuf);
+
+impl ReplayFile {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Option<Self> {
+        args.find(|arg| arg == "--replay")?;
+        args.next().map(|path| ReplayFile(path.into()))
+    }
+}
+
+/// Whether matches get recorded and how many recordings to keep, from `--no-replays` and
+/// `--keep-replays <count>` on the command line.
+#[derive(Resource)]
+pub struct ReplaySettings {
+    pub record: bool,
+    pub keep: usize,
+}
+
+impl ReplaySettings {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+        let mut settings = ReplaySettings {
+            record: true,
+            keep: DEFAULT_KEEP,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--no-replays" => settings.record = false,
+                "--keep-replays" => match args.next().and_then(|count| count.parse().ok()) {
+                    Some(keep) => settings.keep = keep,
+                    None => warn!("--keep-replays needs a number, keeping {DEFAULT_KEEP}"),
+                },
+                _ => {}
+            }
+        }
+        settings
+    }
+}
+
+/// A recording being played back.
+#[derive(Resource)]
+pub struct Playback {
+    recording: Recording,
+    /// Seconds into the recording.
+    position: f32,
+    /// Index of the next entry to play.
+    next: usize,
+    speed: f32,
+    paused: bool,
+    /// Set after a rewind, so the world is cleared before the recording starts over.
+    rewound: bool,
+    feed: Sender<ReceiveMessage>,
+    outbox: Receiver<SendMessage>,
+}
+
+#[derive(Component)]
+pub struct ReplayStatusText;
+
+/// Puts the recorder in front of the connection as a match starts.
+pub fn start_recording(
+    mut commands: Commands,
+    mut connection_handler: ResMut<ConnectionState>,
+    current_match: Res<CurrentMatch>,
+    playback: Option<Res<Playback>>,
+    recorder: Option<Res<Recorder>>,
+    settings: Res<ReplaySettings>,
+    time: Res<Time>,
+) {
+    if !settings.record || settings.keep == 0 || playback.is_some() || recorder.is_some() {
+        return;
+    }
+    let ConnectionState::Connected(connection) = connection_handler.as_mut() else {
+        return;
+    };
+    let (game_sender, receiver) = flume::unbounded();
+    let (sender, game_receiver) = flume::unbounded();
+    let transport_sender = std::mem::replace(&mut connection.sender, sender);
+    let transport_receiver = std::mem::replace(&mut connection.receiver, receiver);
+    commands.insert_resource(Recorder {
+        recording: Recording {
+            room_id: connection.room_id.clone(),
+            self_id: connection.self_id,
+            players: connection.players.clone(),
+            settings: current_match.settings,
+            started_at: current_match.started_at,
+            entries: vec![],
+        },
+        started: time.elapsed_seconds(),
+        game_sender,
+        game_receiver,
+        transport_sender,
+        transport_receiver,
+    });
+}
+
+fn forward(recorder: &mut Recorder, now: f32) {
+    let at = now - recorder.started;
+    for msg in recorder.game_receiver.try_iter() {
+        let SendMessage::TransportMessage(transport_msg) = &msg;
+        if let Ok(data) = bincode::serialize(transport_msg) {
+            recorder.recording.entries.push((at, Recorded::Sent(data)));
+        }
+        if let Err(err) = recorder.transport_sender.send(msg) {
+            warn!("{err:?}")
+        }
+    }
+    for msg in recorder.transport_receiver.try_iter() {
+        if let ReceiveMessage::DatagramReceived(data) = &msg {
+            recorder
+                .recording
+                .entries
+                .push((at, Recorded::Received(data.clone())));
+        }
+        if let Err(err) = recorder.game_sender.send(msg) {
+            warn!("{err:?}")
+        }
+    }
+}
+
+/// Passes traffic both ways while writing it down.
+pub fn record_traffic(recorder: Option<ResMut<Recorder>>, time: Res<Time>) {
+    if let Some(mut recorder) = recorder {
+        forward(&mut recorder, time.elapsed_seconds());
+    }
+}
+
+/// Hands the connection back to the relay and saves the match once it's over.
+pub fn stop_recording(
+    mut commands: Commands,
+    recorder: Option<ResMut<Recorder>>,
+    mut connection_handler: ResMut<ConnectionState>,
+    settings: Res<ReplaySettings>,
+    time: Res<Time>,
+) {
+    let Some(mut recorder) = recorder else {
+        return;
+    };
+    forward(&mut recorder, time.elapsed_seconds());
+    if let ConnectionState::Connected(connection) = connection_handler.as_mut() {
+        connection.sender = recorder.transport_sender.clone();
+        connection.receiver = recorder.transport_receiver.clone();
+    }
+    if let Err(err) = save(&recorder.recording) {
+        warn!("Couldn't save the replay: {err:?}")
+    }
+    if let Err(err) = prune(settings.keep) {
+        warn!("Couldn't clear out old replays: {err:?}")
+    }
+    commands.remove_resource::<Recorder>();
+}
+
+fn save(recording: &Recording) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(REPLAY_DIR)?;
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let path = PathBuf::from(REPLAY_DIR).join(format!("{}-{seconds}.replay", recording.room_id));
+    bincode::serialize_into(BufWriter::new(File::create(&path)?), recording)?;
+    info!("Saved replay to {}", path.display());
+    Ok(())
+}
+
+/// Deletes the oldest recordings until only `keep` are left.
+fn prune(keep: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let mut replays = vec![];
+    for entry in std::fs::read_dir(REPLAY_DIR)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "replay") {
+            replays.push((entry.metadata()?.modified()?, path));
+        }
+    }
+    replays.sort();
+    for (_, path) in replays.iter().rev().skip(keep) {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn load(path: &Path) -> Result<Recording, Box<dyn std::error::Error>> {
+    let file = BufReader::new(File::open(path)?);
+    Ok(bincode::deserialize_from(file)?)
+}
+
+/// Opens the recording from the command line and starts the match it holds, with nobody
+/// of our own in it.
+pub fn start_replay(
+    mut commands: Commands,
+    file: Option<Res<ReplayFile>>,
+    host: Query<Entity, With<Host>>,
+    camera: Query<Entity, With<MainCamera>>,
+    mut connection_handler: ResMut<ConnectionState>,
+    mut current_match: ResMut<CurrentMatch>,
+    mut next_state: ResMut<NextState<GameStates>>,
+) {
+    let Some(file) = file else {
+        return;
+    };
+    let recording = match load(&file.0) {
+        Ok(recording) => recording,
+        Err(err) => {
+            error!("Couldn't open the replay {}: {err:?}", file.0.display());
+            return;
+        }
+    };
+    // The recorded host makes all the calls, we only watch.
+    for host in host.iter() {
+        commands.entity(host).despawn();
+    }
+    for camera in camera.iter() {
+        commands.entity(camera).insert(FreeCamera);
+    }
+    let (feed, receiver) = flume::unbounded();
+    let (sender, outbox) = flume::unbounded();
+    *connection_handler = ConnectionState::Connected(ConnectionHandler {
+        self_id: None,
+        players: recording.players.clone(),
+        sender,
+        receiver,
+        room_id: recording.room_id.clone(),
+    });
+    *current_match = CurrentMatch {
+        settings: recording.settings,
+        started_at: recording.started_at,
+        results: None,
+        left: None,
+    };
+    next_state.set(GameStates::GamePlay);
+    commands.spawn((
+        ReplayStatusText,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::rgb(0.9, 0.9, 0.9),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.),
+            left: Val::Px(10.),
+            ..default()
+        }),
+    ));
+    commands.insert_resource(Playback {
+        recording,
+        position: 0.0,
+        next: 0,
+        speed: 1.0,
+        paused: false,
+        rewound: false,
+        feed,
+        outbox,
+    });
+}
+
+/// Space pauses, left and right skip, up and down change the speed.
+pub fn control_replay(
+    mut commands: Commands,
+    playback: Option<ResMut<Playback>>,
+    keys: Res<Input<KeyCode>>,
+    snakes: Query<Entity, With<SnakeTag>>,
+    food: Query<Entity, With<Food>>,
+    mut connection_handler: ResMut<ConnectionState>,
+) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+    }
+    if keys.just_pressed(KeyCode::Up) {
+        playback.speed = (playback.speed * 2.0).min(MAX_SPEED);
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        playback.speed = (playback.speed / 2.0).max(MIN_SPEED);
+    }
+    if keys.just_pressed(KeyCode::Right) {
+        playback.position = (playback.position + SEEK_SECONDS).min(playback.recording.length());
+    }
+    if keys.just_pressed(KeyCode::Left) {
+        // Nothing can be played backwards, so start over and catch up to the new spot.
+        playback.position = (playback.position - SEEK_SECONDS).max(0.0);
+        playback.next = 0;
+        playback.rewound = true;
+        for entity in snakes.iter().chain(food.iter()) {
+            commands.entity(entity).despawn_recursive();
+        }
+        if let ConnectionState::Connected(connection) = connection_handler.as_mut() {
+            connection.players = playback.recording.players.clone();
+        }
+    }
+}
+
+/// Feeds the recording to `receive_msgs` as fast as it's being played.
+pub fn play_replay(playback: Option<ResMut<Playback>>, time: Res<Time>) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+    playback.outbox.drain();
+    // Let the rewind clear the world first.
+    if playback.rewound {
+        playback.rewound = false;
+        return;
+    }
+    if !playback.paused {
+        playback.position = (playback.position + time.delta_seconds() * playback.speed)
+            .min(playback.recording.length());
+    }
+    while let Some((at, entry)) = playback.recording.entries.get(playback.next) {
+        if *at > playback.position {
+            break;
+        }
+        if let Some(data) = playback.recording.datagram(entry) {
+            if let Err(err) = playback.feed.send(ReceiveMessage::DatagramReceived(data)) {
+                warn!("{err:?}")
+            }
+        }
+        playback.next += 1;
+    }
+}
+
+/// WASD pans the camera, Q and E zoom out and in.
+pub fn move_free_camera(
+    keys: Res<Input<KeyCode>>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<FreeCamera>>,
+    time: Res<Time>,
+) {
+    let Ok((mut transform, mut projection)) = camera.get_single_mut() else {
+        return;
+    };
+    let mut direction = Vec2::ZERO;
+    for (key, step) in [
+        (KeyCode::W, Vec2::Y),
+        (KeyCode::S, Vec2::NEG_Y),
+        (KeyCode::A, Vec2::NEG_X),
+        (KeyCode::D, Vec2::X),
+    ] {
+        if keys.pressed(key) {
+            direction += step;
+        }
+    }
+    let pan = direction * PAN_SPEED * projection.scale * time.delta_seconds();
+    transform.translation += pan.extend(0.0);
+    if keys.pressed(KeyCode::Q) {
+        projection.scale = (projection.scale * (1.0 + time.delta_seconds())).min(4.0);
+    }
+    if keys.pressed(KeyCode::E) {
+        projection.scale = (projection.scale / (1.0 + time.delta_seconds())).max(0.5);
+    }
+}
+
+pub fn display_replay_status(
+    playback: Option<Res<Playback>>,
+    mut text: Query<&mut Text, With<ReplayStatusText>>,
+) {
+    let (Some(playback), Ok(mut text)) = (playback, text.get_single_mut()) else {
+        return;
+    };
+    let clock = |seconds: f32| format!("{}:{:02}", seconds as u32 / 60, seconds as u32 % 60);
+    let status = format!(
+        "Replay {} / {}  x{}{}  -  Space pause, arrows seek and speed, WASD/QE camera",
+        clock(playback.position),
+        clock(playback.recording.length()),
+        playback.speed,
+        if playback.paused { "  paused" } else { "" },
+    );
+    if let Some(section) = text.sections.first_mut() {
+        if section.value != status {
+            section.value = status;
+        }
+    }
+}
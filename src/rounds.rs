@@ -0,0 +1,426 @@
+use bevy::prelude::*;
+
+use crate::{
+    arena::Portal,
+    effects::EffectsHud,
+    emotes::{EmoteBubble, EmotePicker, EmoteToggleButton, PingMarker},
+    food::{Food, FoodPointer},
+    game_mode::{CurrentMatch, MatchOutcome, MatchResults, RoundLimit},
+    game_over::GameOvermenu,
+    networking::{ConnectionHandler, ConnectionState, GameClock, SendMessage, TransportMessage},
+    objectives::Objective,
+    obstacles::Obstacle,
+    profile::NameTag,
+    scoring::Scoreboard,
+    spectator::SpectatorStatusText,
+    teams::{team_name, team_scores},
+    GameStates, Host, SnakeTag,
+};
+
+/// Snakes of players that just joined show up with their first update, so the host waits
+/// a little before deciding who is left.
+const WINNER_GRACE: f32 = 5.0;
+
+/// Ends the round on the host and sends everyone the results table.
+pub fn finish_match(
+    current_match: &mut CurrentMatch,
+    connection: &ConnectionHandler,
+    outcome: MatchOutcome,
+) {
+    let mut scores: Vec<(u32, u32)> = connection
+        .playing()
+        .map(|player| (player.user_id, player.score))
+        .collect();
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    let results = MatchResults { outcome, scores };
+    current_match.results = Some(results.clone());
+    if let Err(err) =
+        connection
+            .sender
+            .send(SendMessage::TransportMessage(TransportMessage::MatchOver(
+                results,
+            )))
+    {
+        warn!("{err:?}")
+    }
+}
+
+pub fn check_round_limit(
+    host: Query<&Host>,
+    mut current_match: ResMut<CurrentMatch>,
+    clock: Res<GameClock>,
+    time: Res<Time>,
+    connection_handler: Res<ConnectionState>,
+) {
+    if host.is_empty() || current_match.results.is_some() {
+        return;
+    }
+    let Some(started_at) = current_match.started_at else {
+        return;
+    };
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    // Teams play for their summed score.
+    let standings: Vec<(MatchOutcome, u32)> = if current_match.settings.teams.enabled() {
+        team_scores(&connection.players)
+            .into_iter()
+            .map(|(team, score)| (MatchOutcome::TeamWinner(team), score))
+            .collect()
+    } else {
+        let mut standings: Vec<(MatchOutcome, u32)> = connection
+            .playing()
+            .map(|p| (MatchOutcome::Winner(p.user_id), p.score))
+            .collect();
+        standings.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        standings
+    };
+    let outcome = match current_match.settings.limit {
+        RoundLimit::Endless => return,
+        RoundLimit::Timed { seconds } => {
+            if clock.now(&time) - started_at < seconds {
+                return;
+            }
+            match standings[..] {
+                [(leader, _)] => leader,
+                [(leader, best), (_, second), ..] if best > second => leader,
+                _ => MatchOutcome::Draw,
+            }
+        }
+        RoundLimit::ScoreTarget { score } => match standings.first() {
+            Some((leader, best)) if *best >= score => *leader,
+            _ => return,
+        },
+    };
+    finish_match(&mut current_match, connection, outcome);
+}
+
+/// Ends the match on the host once the mode's own win condition is met, like the last snake
+/// standing in a battle royale.
+pub fn check_mode_outcome(
+    host: Query<&Host>,
+    snakes: Query<&SnakeTag>,
+    mut current_match: ResMut<CurrentMatch>,
+    clock: Res<GameClock>,
+    time: Res<Time>,
+    connection_handler: Res<ConnectionState>,
+) {
+    if host.is_empty() || current_match.results.is_some() {
+        return;
+    }
+    let Some(started_at) = current_match.started_at else {
+        return;
+    };
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    // Nobody to win against.
+    if connection.playing().count() < 2 || clock.now(&time) - started_at < WINNER_GRACE {
+        return;
+    }
+    let alive: Vec<u32> = snakes
+        .iter()
+        .filter_map(|tag| match tag {
+            SnakeTag::SelfPlayerSnake => connection.self_id,
+            SnakeTag::OtherPlayerSnake(id) => Some(*id),
+        })
+        .collect();
+    let teams = current_match.settings.teams.enabled();
+    let team = |id: u32| {
+        connection
+            .players
+            .iter()
+            .find(|p| p.user_id == id)
+            .and_then(|p| p.team)
+            .filter(|_| teams)
+    };
+    let Some(outcome) = current_match.settings.mode.rules().outcome(&alive, &team) else {
+        return;
+    };
+    finish_match(&mut current_match, connection, outcome);
+}
+
+pub fn enter_game_over(
+    current_match: Res<CurrentMatch>,
+    mut next_state: ResMut<NextState<GameStates>>,
+) {
+    if current_match.results.is_some() {
+        next_state.set(GameStates::GameOver);
+    }
+}
+
+#[derive(Component)]
+pub struct RoundStatusText;
+
+pub fn setup_round_hud(mut commands: Commands, current_match: Res<CurrentMatch>) {
+    if current_match.settings.limit == RoundLimit::Endless {
+        return;
+    }
+    commands.spawn((
+        RoundStatusText,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: Color::rgb(0.9, 0.9, 0.9),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(50.),
+            left: Val::Percent(40.),
+            ..default()
+        }),
+    ));
+}
+
+pub fn display_round_status(
+    current_match: Res<CurrentMatch>,
+    clock: Res<GameClock>,
+    time: Res<Time>,
+    mut status: Query<&mut Text, With<RoundStatusText>>,
+) {
+    let (Ok(mut text), Some(started_at)) = (status.get_single_mut(), current_match.started_at)
+    else {
+        return;
+    };
+    let status = match current_match.settings.limit {
+        RoundLimit::Endless => return,
+        RoundLimit::Timed { seconds } => {
+            let left = (seconds - (clock.now(&time) - started_at)).max(0.0).ceil() as u32;
+            format!("{}:{:02} left", left / 60, left % 60)
+        }
+        RoundLimit::ScoreTarget { score } => format!("First to {score} wins"),
+    };
+    if let Some(section) = text.sections.first_mut() {
+        if section.value != status {
+            section.value = status;
+        }
+    }
+}
+
+/// Clears out everything a round left behind, so a rematch starts from an empty field.
+pub fn clean_gameplay(
+    mut commands: Commands,
+    leftovers: Query<
+        Entity,
+        Or<(
+            With<SnakeTag>,
+            With<Food>,
+            With<Obstacle>,
+            With<Portal>,
+            With<Scoreboard>,
+            With<EffectsHud>,
+            With<GameOvermenu>,
+            With<RoundStatusText>,
+            With<Objective>,
+            With<SpectatorStatusText>,
+            With<NameTag>,
+            With<EmoteToggleButton>,
+            With<EmotePicker>,
+            With<EmoteBubble>,
+            With<PingMarker>,
+        )>,
+    >,
+    mut food_pointers: Query<&mut Visibility, With<FoodPointer>>,
+) {
+    for entity in leftovers.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for mut visibility in food_pointers.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+#[derive(Component)]
+pub struct ResultsScreen;
+
+#[derive(Component)]
+pub struct BackToLobbyButton;
+
+pub fn setup_results_screen(
+    mut commands: Commands,
+    current_match: Res<CurrentMatch>,
+    connection_handler: Res<ConnectionState>,
+    host: Query<&Host>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let Some(results) = current_match.results.as_ref() else {
+        return;
+    };
+    let title = match results.outcome {
+        MatchOutcome::Winner(id) if Some(id) == connection.self_id => "You win!".to_string(),
+        MatchOutcome::Winner(id) => format!(
+            "{} wins!",
+            connection
+                .players
+                .iter()
+                .find(|p| p.user_id == id)
+                .map_or_else(|| format!("Player {id}"), |p| p.name())
+        ),
+        MatchOutcome::TeamWinner(team) => format!("{} team wins!", team_name(team)),
+        MatchOutcome::Draw => "It's a draw".to_string(),
+    };
+    let team_totals = team_scores(&connection.players)
+        .iter()
+        .map(|(team, score)| format!("{} {}", team_name(*team), score))
+        .collect::<Vec<_>>()
+        .join("   ");
+    let text_style = |font_size: f32| TextStyle {
+        font_size,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..default()
+    };
+    commands
+        .spawn((
+            ResultsScreen,
+            NodeBundle {
+                style: Style {
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::SpaceEvenly,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0.2, 0.2, 0.2, 0.7).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(title, text_style(50.0)));
+            if !team_totals.is_empty() {
+                parent.spawn(TextBundle::from_section(team_totals, text_style(30.0)));
+            }
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(10.),
+                        padding: UiRect::all(Val::Px(20.)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.5, 0.5, 0.5).into(),
+                    ..default()
+                })
+                .with_children(|table| {
+                    for (rank, (user_id, score)) in results.scores.iter().enumerate() {
+                        let player = connection.players.iter().find(|p| &p.user_id == user_id);
+                        table
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Row,
+                                    align_items: AlignItems::Center,
+                                    column_gap: Val::Px(10.),
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|row| {
+                                row.spawn(TextBundle::from_section(
+                                    format!("{}.", rank + 1),
+                                    text_style(30.0),
+                                ));
+                                row.spawn(NodeBundle {
+                                    background_color: player
+                                        .map(|p| p.snake_color())
+                                        .unwrap_or(Color::GRAY)
+                                        .into(),
+                                    style: Style {
+                                        width: Val::Px(30.),
+                                        height: Val::Px(30.),
+                                        ..default()
+                                    },
+                                    ..default()
+                                });
+                                row.spawn(TextBundle::from_section(
+                                    format!(
+                                        "{}{}",
+                                        player.map_or_else(
+                                            || format!("Player {user_id}"),
+                                            |p| p.name()
+                                        ),
+                                        if Some(*user_id) == connection.self_id {
+                                            " (You)"
+                                        } else {
+                                            ""
+                                        }
+                                    ),
+                                    text_style(30.0),
+                                ));
+                                row.spawn(TextBundle::from_section(
+                                    score.to_string(),
+                                    text_style(30.0),
+                                ));
+                            });
+                    }
+                });
+            if host.is_empty() {
+                parent.spawn(TextBundle::from_section(
+                    "Waiting for the host to start a rematch",
+                    text_style(24.0),
+                ));
+            } else {
+                parent
+                    .spawn((
+                        BackToLobbyButton,
+                        ButtonBundle {
+                            style: Style {
+                                height: Val::Px(65.),
+                                padding: UiRect::horizontal(Val::Px(10.)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section("Back to lobby", text_style(40.0)));
+                    });
+            }
+        });
+}
+
+pub fn results_handle_button(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<BackToLobbyButton>)>,
+    mut current_match: ResMut<CurrentMatch>,
+    mut next_state: ResMut<NextState<GameStates>>,
+    connection_handler: Res<ConnectionState>,
+) {
+    for interaction in &interaction_query {
+        if Interaction::Pressed == *interaction {
+            return_to_lobby(&connection_handler, &mut current_match, &mut next_state);
+        }
+    }
+}
+
+/// Takes everyone from the results back to the lobby, on the host.
+pub fn return_to_lobby(
+    connection_handler: &ConnectionState,
+    current_match: &mut CurrentMatch,
+    next_state: &mut NextState<GameStates>,
+) {
+    if let Some(started_at) = current_match.started_at {
+        if let ConnectionState::Connected(connection) = connection_handler {
+            if let Err(err) = connection.sender.send(SendMessage::TransportMessage(
+                TransportMessage::ReturnToLobby(started_at),
+            )) {
+                warn!("{err:?}")
+            }
+        }
+        current_match.leave(started_at);
+    }
+    next_state.set(GameStates::Lobby);
+}
+
+pub fn clean_results_screen(mut commands: Commands, screen: Query<Entity, With<ResultsScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
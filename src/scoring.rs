@@ -1,11 +1,18 @@
 use bevy::prelude::*;
 
-use crate::{networking::ConnectionState, SnakeTag};
+use crate::{
+    game_mode::CurrentMatch,
+    networking::{ConnectionState, PlayersChanged},
+    teams::{team_color, team_name, team_scores},
+    SnakeTag,
+};
 
 pub fn sync_scores(
     snake_tag: Query<(&SnakeTag, &Children)>,
     mut connection_handler: ResMut<ConnectionState>,
+    current_match: Res<CurrentMatch>,
 ) {
+    let rules = current_match.settings.mode.rules();
     if let ConnectionState::Connected(connection) = connection_handler.as_mut() {
         for (snek, children) in snake_tag.iter() {
             if children.len() >= 3 {
@@ -21,8 +28,8 @@ pub fn sync_scores(
                         connection.players.iter_mut().find(|p| &p.user_id == id)
                     }
                 };
-                if let Some(player) = player {
-                    player.score = (children.len() - 3) as u32;
+                if let (Some(player), Some(score)) = (player, rules.length_score(children.len())) {
+                    player.score = score;
                     if player.score > player.highest_score {
                         player.highest_score = player.score;
                     }
@@ -41,6 +48,14 @@ pub struct ScoreContainer(u32);
 #[derive(Component)]
 pub struct ScoreText(u32);
 
+/// Colour square in front of a player's score.
+#[derive(Component)]
+pub struct ScoreSwatch(u32);
+
+/// Summed score of a team, listed above the players.
+#[derive(Component)]
+pub struct TeamScoreText(u8);
+
 pub fn setup_score(mut commands: Commands) {
     commands.spawn((
         Scoreboard,
@@ -64,16 +79,69 @@ pub fn setup_score(mut commands: Commands) {
 
 pub fn display_scores(
     connection_handler: Res<ConnectionState>,
-    mut q_score_text: Query<(&mut Text, &ScoreText)>,
+    mut players_changed: EventReader<PlayersChanged>,
+    mut q_score_text: Query<(&mut Text, &ScoreText), Without<TeamScoreText>>,
+    mut q_team_score_text: Query<(Entity, &mut Text, &TeamScoreText)>,
+    mut q_swatch: Query<(&mut BackgroundColor, &ScoreSwatch)>,
+    q_score_container: Query<(Entity, &ScoreContainer)>,
     mut commands: Commands,
     q_scoreboard: Query<Entity, With<Scoreboard>>,
 ) {
+    let players_changed = players_changed.iter().last().is_some();
     if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
-        for player in connection.players.iter() {
+        let teams = team_scores(&connection.players);
+        // Colours and who is still in the room only change along with the player list.
+        if players_changed {
+            for (entity, container) in q_score_container.iter() {
+                if !connection.playing().any(|p| p.user_id == container.0) {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+            for (entity, _, team_text) in q_team_score_text.iter() {
+                if !teams.iter().any(|(team, _)| *team == team_text.0) {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+            for (mut background, swatch) in q_swatch.iter_mut() {
+                if let Some(player) = connection.playing().find(|p| p.user_id == swatch.0) {
+                    *background = player.snake_color().into();
+                }
+            }
+        }
+        for (team, score) in teams {
+            let scoretxt = format!("{} {}", team_name(team), score);
+            if let Some((_, mut text, _)) = q_team_score_text.iter_mut().find(|p| p.2 .0 == team) {
+                if let Some(section) = text.sections.first_mut() {
+                    if section.value != scoretxt {
+                        section.value = scoretxt;
+                    }
+                }
+            } else if let Ok(scoreboard) = q_scoreboard.get_single() {
+                commands.entity(scoreboard).with_children(|parent| {
+                    parent.spawn((
+                        TeamScoreText(team),
+                        TextBundle::from_section(
+                            scoretxt,
+                            TextStyle {
+                                font_size: 24.0,
+                                color: team_color(team),
+                                ..Default::default()
+                            },
+                        ),
+                    ));
+                });
+            }
+        }
+        for player in connection.playing() {
             let scoretxt = if player.score == player.highest_score {
-                player.score.to_string()
+                format!("{}  {}", player.name(), player.score)
             } else {
-                format!("{} ({})", player.score, player.highest_score)
+                format!(
+                    "{}  {} ({})",
+                    player.name(),
+                    player.score,
+                    player.highest_score
+                )
             };
             let score_text = q_score_text.iter_mut().find(|p| p.1 .0 == player.user_id);
             if let Some(mut text) = score_text {
@@ -101,24 +169,27 @@ pub fn display_scores(
                             },
                         ))
                         .with_children(|parent| {
-                            parent.spawn(NodeBundle {
-                                border_color: BorderColor::DEFAULT,
-                                background_color: player.color.into(),
-                                style: Style {
-                                    width: Val::Px(20.),
-                                    border: UiRect::all(Val::Px(
-                                        if Some(player.user_id) == connection.self_id {
-                                            1.
-                                        } else {
-                                            0.
-                                        },
-                                    )),
-                                    height: Val::Px(20.),
-                                    margin: UiRect::right(Val::Px(5.)),
+                            parent.spawn((
+                                ScoreSwatch(player.user_id),
+                                NodeBundle {
+                                    border_color: BorderColor::DEFAULT,
+                                    background_color: player.snake_color().into(),
+                                    style: Style {
+                                        width: Val::Px(20.),
+                                        border: UiRect::all(Val::Px(
+                                            if Some(player.user_id) == connection.self_id {
+                                                1.
+                                            } else {
+                                                0.
+                                            },
+                                        )),
+                                        height: Val::Px(20.),
+                                        margin: UiRect::right(Val::Px(5.)),
+                                        ..Default::default()
+                                    },
                                     ..Default::default()
                                 },
-                                ..Default::default()
-                            });
+                            ));
                             parent.spawn((
                                 ScoreText(player.user_id),
                                 TextBundle::from_section(
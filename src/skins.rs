@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::networking::{player_color, ConnectionState, PlayerProp, PlayersChanged};
+
+/// Colours players pick from, far enough apart that no two snakes look alike.
+pub const PALETTE: [Color; 12] = [
+    Color::rgb(0.95, 0.25, 0.25),
+    Color::rgb(0.95, 0.55, 0.15),
+    Color::rgb(0.95, 0.85, 0.2),
+    Color::rgb(0.6, 0.9, 0.2),
+    Color::rgb(0.15, 0.75, 0.35),
+    Color::rgb(0.2, 0.85, 0.8),
+    Color::rgb(0.25, 0.6, 0.95),
+    Color::rgb(0.3, 0.3, 0.9),
+    Color::rgb(0.6, 0.3, 0.9),
+    Color::rgb(0.9, 0.35, 0.8),
+    Color::rgb(0.95, 0.95, 0.95),
+    Color::rgb(0.45, 0.3, 0.2),
+];
+
+/// Pattern a snake's cells are painted with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Skin {
+    #[default]
+    Plain,
+    Stripes,
+    Gradient,
+    Outline,
+}
+
+impl Skin {
+    pub const ALL: [Skin; 4] = [Skin::Plain, Skin::Stripes, Skin::Gradient, Skin::Outline];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Skin::Plain => "Plain",
+            Skin::Stripes => "Stripes",
+            Skin::Gradient => "Gradient",
+            Skin::Outline => "Outline",
+        }
+    }
+
+    /// Colour of the cell `index` places behind the head of a snake `length` cells long.
+    pub fn cell_color(&self, color: Color, index: usize, length: usize) -> Color {
+        match self {
+            Skin::Plain | Skin::Outline => color,
+            Skin::Stripes if index % 4 >= 2 => shade(color, 0.55),
+            Skin::Stripes => color,
+            Skin::Gradient => {
+                let along = index as f32 / length.saturating_sub(1).max(1) as f32;
+                shade(color, 1.0 - along * 0.65)
+            }
+        }
+    }
+}
+
+fn shade(color: Color, factor: f32) -> Color {
+    Color::rgba(
+        color.r() * factor,
+        color.g() * factor,
+        color.b() * factor,
+        color.a(),
+    )
+}
+
+/// Textures for skins that need more than a flat colour.
+#[derive(Resource)]
+pub struct SkinTextures {
+    /// White with a grey rim, tinted with the snake's colour.
+    pub outline: Handle<Image>,
+}
+
+pub fn load_skin_textures(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SkinTextures {
+        outline: asset_server.load("skin-outline.png"),
+    });
+}
+
+/// Gives every player with a snake a palette colour nobody else has.
+///
+/// Players who picked a colour come first, the one with the lowest id winning a colour two
+/// of them want; everyone else gets the first free colour from where their id lands. Every
+/// peer runs this over the same players, so they all agree. Returns whether anyone's colour
+/// changed.
+pub fn resolve_colors(players: &mut [PlayerProp]) -> bool {
+    let mut order: Vec<usize> = (0..players.len())
+        .filter(|index| !players[*index].spectator)
+        .collect();
+    order.sort_by_key(|index| {
+        let player = &players[*index];
+        (player.profile.color.is_none(), player.user_id)
+    });
+    let mut taken: Vec<usize> = vec![];
+    let mut changed = false;
+    for index in order {
+        let player = &mut players[index];
+        let wanted = player
+            .profile
+            .color
+            .map_or(player.user_id as usize, |color| color as usize);
+        let slot = (0..PALETTE.len())
+            .map(|offset| (wanted + offset) % PALETTE.len())
+            .find(|slot| !taken.contains(slot));
+        // More players than colours, the rest fall back to their own hue.
+        let color = match slot {
+            Some(slot) => {
+                taken.push(slot);
+                PALETTE[slot]
+            }
+            None => player_color(player.user_id),
+        };
+        if player.color != color {
+            player.color = color;
+            changed = true;
+        }
+    }
+    changed
+}
+
+pub fn resolve_player_colors(
+    mut connection_handler: ResMut<ConnectionState>,
+    mut players_changed: EventWriter<PlayersChanged>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_mut() else {
+        return;
+    };
+    if resolve_colors(&mut connection.players) {
+        players_changed.send(PlayersChanged {
+            players: connection.players.clone(),
+            self_player: connection.self_id,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::Profile;
+
+    /// Players in the room by id and picked colour, all still white.
+    fn room(picks: &[(u32, Option<u8>)]) -> Vec<PlayerProp> {
+        picks
+            .iter()
+            .map(|(user_id, color)| PlayerProp {
+                last_update_time: None,
+                start_time: None,
+                user_id: *user_id,
+                color: Color::WHITE,
+                score: 0,
+                highest_score: 0,
+                team: None,
+                bot: None,
+                seat: None,
+                spectator: false,
+                profile: Profile {
+                    color: *color,
+                    ..default()
+                },
+                ready: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lowest_id_wins_a_clash() {
+        let mut players = room(&[(7, Some(3)), (2, Some(3))]);
+        assert!(resolve_colors(&mut players));
+        assert_eq!(players[1].color, PALETTE[3]);
+        assert_eq!(players[0].color, PALETTE[4]);
+    }
+
+    #[test]
+    fn picked_colours_come_before_the_rest() {
+        // Id 5 would land on colour 5 but someone picked it.
+        let mut players = room(&[(5, None), (9, Some(5))]);
+        resolve_colors(&mut players);
+        assert_eq!(players[1].color, PALETTE[5]);
+        assert_eq!(players[0].color, PALETTE[6]);
+    }
+
+    #[test]
+    fn spectators_take_no_colour() {
+        let mut players = room(&[(1, Some(0)), (2, Some(0))]);
+        players[0].spectator = true;
+        resolve_colors(&mut players);
+        assert_eq!(players[0].color, Color::WHITE);
+        assert_eq!(players[1].color, PALETTE[0]);
+    }
+
+    #[test]
+    fn resolving_twice_changes_nothing() {
+        let picks: Vec<_> = (0..20).map(|id| (id, Some(0))).collect();
+        let mut players = room(&picks);
+        assert!(resolve_colors(&mut players));
+        assert!(!resolve_colors(&mut players));
+        // Everyone past the palette falls back to their own hue.
+        assert_eq!(players[19].color, player_color(19));
+    }
+}
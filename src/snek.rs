@@ -1,9 +1,16 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use rand::Rng;
 
 use crate::{
-    networking::ConnectionState, CellTag, ChangeDirection, GameConfig, Head, HeadSensor,
-    LastMoveId, MoveId, Moves, Player, Snake, SnakeCell, SnakeTag, Tail,
+    arena::Arena,
+    effects::{ActiveEffects, EffectKind},
+    game_mode::{CurrentMatch, ModeRules},
+    networking::{ConnectionState, SnakeSyncTimer},
+    obstacles::Obstacle,
+    split_screen::{local_player_id, own_seats, KeySet},
+    CellTag, ChangeDirection, GameConfig, Head, HeadSensor, InputsActions, LastMoveId, MoveId,
+    Moves, Player, PlayerInput, Snake, SnakeCell, SnakeSpeed, SnakeTag, Tail,
 };
 
 #[derive(Event)]
@@ -11,145 +18,218 @@ pub struct KillSnake {
     pub snake_id: Entity,
 }
 
+/// Spawns the snake of the player using `keys` at this machine, the owner's when `None`.
 #[derive(Event)]
-pub struct SpawnSnake;
+pub struct SpawnSnake {
+    pub keys: Option<KeySet>,
+}
+
+pub fn setup_snek(
+    mut spawn_snek_writer: EventWriter<SpawnSnake>,
+    connection_handler: Res<ConnectionState>,
+) {
+    spawn_snek_writer.send(SpawnSnake { keys: None });
+    if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
+        for keys in own_seats(connection) {
+            spawn_snek_writer.send(SpawnSnake { keys: Some(keys) });
+        }
+    }
+}
+
+/// Spawn points tried before settling for the roomiest one.
+const SPAWN_CANDIDATES: usize = 32;
+
+/// Distance a spawn point wants to keep from bodies and obstacles.
+const SPAWN_CLEARANCE: f32 = 250.0;
+
+/// How far ahead of a new head the way should be clear, and inside the arena.
+const SPAWN_LOOKAHEAD: f32 = 300.0;
 
-pub fn setup_snek(mut spawn_snek_writer: EventWriter<SpawnSnake>) {
-    spawn_snek_writer.send(SpawnSnake);
+const CARDINALS: [Vec2; 4] = [Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::NEG_Y];
+
+/// How far from the arena edge snakes spawn.
+pub const SPAWN_MARGIN: f32 = 100.0;
+
+/// Picks where a new snake starts and which way it faces.
+///
+/// Candidates too close to `hazards` are rejected; if none is clear the one furthest from
+/// any hazard wins. The snake then faces the direction with the fewest hazards ahead that
+/// doesn't run it out of the arena.
+pub fn pick_spawn(
+    arena: &Arena,
+    rules: &dyn ModeRules,
+    margin: f32,
+    hazards: &[Vec2],
+) -> (Vec2, Vec2) {
+    pick_spawn_with(&mut rand::thread_rng(), arena, rules, margin, hazards)
+}
+
+/// [`pick_spawn`] with the candidates drawn from `rng`.
+fn pick_spawn_with(
+    rng: &mut impl Rng,
+    arena: &Arena,
+    rules: &dyn ModeRules,
+    margin: f32,
+    hazards: &[Vec2],
+) -> (Vec2, Vec2) {
+    let clearance = |point: Vec2| {
+        hazards
+            .iter()
+            .map(|hazard| hazard.distance(point))
+            .fold(f32::INFINITY, f32::min)
+    };
+    let mut best = (arena.random_point(rng, margin), 0.0);
+    for _ in 0..SPAWN_CANDIDATES {
+        let candidate = arena.random_point(rng, margin);
+        let room = clearance(candidate);
+        if room > best.1 {
+            best = (candidate, room);
+        }
+        if room >= SPAWN_CLEARANCE {
+            break;
+        }
+    }
+    let position = best.0;
+
+    let crowding = |direction: Vec2| {
+        hazards
+            .iter()
+            .filter(|hazard| {
+                let offset = **hazard - position;
+                offset.length() < SPAWN_LOOKAHEAD && offset.dot(direction) > 0.0
+            })
+            .count()
+    };
+    let direction = CARDINALS
+        .into_iter()
+        .filter(|direction| {
+            !rules.edge_kills(arena) || arena.contains(position + *direction * SPAWN_LOOKAHEAD, 0.0)
+        })
+        .min_by_key(|direction| crowding(*direction))
+        // Nowhere is inside the arena that far ahead, head for the middle instead.
+        .unwrap_or_else(|| {
+            let inward = -position;
+            if inward.x.abs() > inward.y.abs() {
+                Vec2::new(inward.x.signum(), 0.0)
+            } else {
+                Vec2::new(0.0, inward.y.signum())
+            }
+        });
+    (position, direction)
 }
 
 pub fn spawn_snek(
     config: Res<GameConfig>,
+    arena: Res<Arena>,
+    current_match: Res<CurrentMatch>,
     mut commands: Commands,
     connection_handler: Res<ConnectionState>,
+    cells: Query<&GlobalTransform, With<CellTag>>,
+    obstacles: Query<&GlobalTransform, With<Obstacle>>,
     mut spawn_snek_reader: EventReader<SpawnSnake>,
 ) {
-    for _event in spawn_snek_reader.iter() {
+    for event in spawn_snek_reader.iter() {
         if let ConnectionState::Connected(connection) = connection_handler.as_ref() {
-            let Some(player_id) = connection.self_id else {
+            let Some(self_id) = connection.self_id else {
                 return;
             };
+            let (player_id, tag) = match event.keys {
+                Some(keys) => {
+                    let user_id = local_player_id(self_id, keys);
+                    (user_id, SnakeTag::OtherPlayerSnake(user_id))
+                }
+                None => (self_id, SnakeTag::SelfPlayerSnake),
+            };
             let Some(player) = connection.players.iter().find(|p| p.user_id == player_id) else {
-                return;
+                continue;
             };
+            if player.spectator {
+                continue;
+            }
 
             let collider_size = (config.cell_size.0 / 2.0, config.cell_size.1 / 2.0);
-            let cell_size = config.cell_size;
+            let cell_size = Vec2::new(config.cell_size.0, config.cell_size.1);
 
-            let initial_position = {
-                let rad = rand::random::<f32>() * 900.0;
-                let angle = rand::random::<f32>() * std::f32::consts::PI * 2.0;
-                let (sin, cos) = angle.sin_cos();
-                (rad * sin, rad * cos)
+            let hazards: Vec<Vec2> = cells
+                .iter()
+                .chain(obstacles.iter())
+                .map(|transform| transform.translation().truncate())
+                .collect();
+            let (initial_position, direction) = pick_spawn(
+                &arena,
+                current_match.settings.mode.rules(),
+                SPAWN_MARGIN,
+                &hazards,
+            );
+            let cell_transform = |index: f32| {
+                Transform::from_translation(
+                    (initial_position - direction * cell_size * index).extend(1.),
+                )
+            };
+            let new_cell = || SnakeCell {
+                cell_tag: CellTag(rand::random()),
+                collider: Collider::cuboid(collider_size.0, collider_size.1),
+                sensor: Sensor,
+                direction: crate::Direction(direction),
+                sprite: SpriteBundle {
+                    sprite: Sprite {
+                        color: player.snake_color(),
+                        custom_size: Some(cell_size),
+                        ..default()
+                    },
+                    ..default()
+                },
+                move_id: MoveId(0),
+            };
+            let sensor_collider = if direction.x != 0.0 {
+                Collider::cuboid(1.0, collider_size.1)
+            } else {
+                Collider::cuboid(collider_size.0, 1.0)
             };
 
+            let mut effects = ActiveEffects::default();
+            if config.spawn_protection > 0.0 {
+                effects.add(EffectKind::SpawnProtection, config.spawn_protection);
+            }
             let player_snake = commands
                 .spawn((
                     Snake {
-                        tag: SnakeTag::SelfPlayerSnake,
+                        tag,
                         spatial: Default::default(),
 
                         lastmove: LastMoveId(0),
                         moves: Moves { moves: vec![] },
+                        effects,
+                        speed: SnakeSpeed(config.speed),
                     },
-                    Player,
+                    Player { keys: event.keys },
                 ))
                 .id();
+            let mut head = new_cell();
+            head.sprite.transform = cell_transform(0.0);
             let cell1 = commands
-                .spawn((
-                    SnakeCell {
-                        cell_tag: CellTag(rand::random()),
-                        collider: Collider::cuboid(collider_size.0, collider_size.1),
-                        sensor: Sensor,
-                        direction: crate::Direction(Vec2 { x: 1.0, y: 0.0 }),
-                        sprite: SpriteBundle {
-                            sprite: Sprite {
-                                color: player.color,
-                                custom_size: Some(Vec2::new(cell_size.0, cell_size.1)),
-                                ..default()
-                            },
-                            transform: Transform::from_translation(Vec3::new(
-                                initial_position.0,
-                                initial_position.1,
-                                1.,
-                            )),
-                            ..default()
-                        },
-                        move_id: MoveId(0),
-                    },
-                    Head,
-                    CollisionGroups::new(Group::NONE, Group::NONE),
-                ))
+                .spawn((head, Head, CollisionGroups::new(Group::NONE, Group::NONE)))
                 .with_children(|head| {
-                    head.spawn(Collider::cuboid(1.0, collider_size.1))
+                    head.spawn(sensor_collider)
                         .insert(RigidBody::KinematicPositionBased)
                         .insert(Ccd::enabled())
                         .insert(HeadSensor)
                         .insert(ActiveCollisionTypes::all())
                         .insert(ActiveEvents::COLLISION_EVENTS)
                         .insert(TransformBundle::from_transform(
-                            Transform::from_translation(Vec3 {
-                                x: cell_size.0 / 2.0,
-                                y: 0.0,
-                                z: 0.0,
-                            }),
+                            Transform::from_translation((direction * cell_size / 2.0).extend(0.0)),
                         ));
                 })
                 .id();
 
+            let mut body = new_cell();
+            body.sprite.transform = cell_transform(1.0);
             let cell2 = commands
-                .spawn((
-                    SnakeCell {
-                        cell_tag: CellTag(rand::random()),
-
-                        collider: Collider::cuboid(collider_size.0, collider_size.1),
-                        sensor: Sensor,
-                        direction: crate::Direction(Vec2 { x: 1.0, y: 0.0 }),
-
-                        sprite: SpriteBundle {
-                            sprite: Sprite {
-                                color: player.color,
-                                custom_size: Some(Vec2::new(cell_size.0, cell_size.1)),
-                                ..default()
-                            },
-                            transform: Transform::from_translation(Vec3::new(
-                                initial_position.0 - cell_size.0,
-                                initial_position.1,
-                                1.,
-                            )),
-                            ..default()
-                        },
-                        move_id: MoveId(0),
-                    },
-                    CollisionGroups::new(Group::NONE, Group::NONE),
-                ))
-                .id();
-            let cell3 = commands
-                .spawn((
-                    SnakeCell {
-                        cell_tag: CellTag(rand::random()),
-
-                        collider: Collider::cuboid(collider_size.0, collider_size.1),
-                        sensor: Sensor,
-                        direction: crate::Direction(Vec2 { x: 1.0, y: 0.0 }),
-                        sprite: SpriteBundle {
-                            sprite: Sprite {
-                                color: player.color,
-                                custom_size: Some(Vec2::new(cell_size.0, cell_size.1)),
-                                ..default()
-                            },
-                            transform: Transform::from_translation(Vec3::new(
-                                initial_position.0 - (cell_size.0 * 2.0),
-                                initial_position.1,
-                                1.,
-                            )),
-                            ..default()
-                        },
-                        move_id: MoveId(0),
-                    },
-                    Tail,
-                ))
+                .spawn((body, CollisionGroups::new(Group::NONE, Group::NONE)))
                 .id();
+            let mut tail = new_cell();
+            tail.sprite.transform = cell_transform(2.0);
+            let cell3 = commands.spawn((tail, Tail)).id();
             commands
                 .entity(player_snake)
                 .push_children(&[cell1, cell2, cell3]);
@@ -157,6 +237,108 @@ pub fn spawn_snek(
     }
 }
 
+/// Smallest number of cells a snake can be shrunk to.
+pub const MIN_SNAKE_CELLS: usize = 3;
+
+/// How much faster a boosting snake moves.
+const BOOST_MULTIPLIER: f32 = 1.8;
+
+/// How often a boosting snake loses a tail cell.
+const BOOST_SHED_SECONDS: f32 = 0.4;
+
+/// Marks a snake at this machine as boosting; it drops a tail cell every time the timer fires.
+#[derive(Component)]
+pub struct Boost {
+    shed_timer: Timer,
+}
+
+pub fn apply_boost(
+    mut commands: Commands,
+    mut inputs: EventReader<PlayerInput>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+    mut snakes: Query<(
+        Entity,
+        &Player,
+        &Children,
+        &mut SnakeSpeed,
+        &mut Moves,
+        Option<&mut Boost>,
+    )>,
+    cell_move_ids: Query<&MoveId, With<CellTag>>,
+    mut sync_timer: ResMut<SnakeSyncTimer>,
+) {
+    let pressed: Vec<(Option<KeySet>, bool)> = inputs
+        .iter()
+        .filter_map(|input| match input.action {
+            InputsActions::Boost(pressed) => Some((input.keys, pressed)),
+            _ => None,
+        })
+        .collect();
+    let mut changed = false;
+    for (snake, player, children, mut speed, mut moves, boost) in snakes.iter_mut() {
+        let mut boosting = boost.is_some();
+        for (keys, is_pressed) in pressed.iter() {
+            if *keys == player.keys {
+                boosting = *is_pressed;
+            }
+        }
+        if boosting && children.len() <= MIN_SNAKE_CELLS {
+            boosting = false;
+        }
+
+        match (boosting, boost) {
+            (true, Some(mut boost)) => {
+                if boost.shed_timer.tick(time.delta()).just_finished() {
+                    shed_tail_cells(&mut commands, children, &cell_move_ids, &mut moves, 1);
+                }
+            }
+            (true, None) => {
+                commands.entity(snake).insert(Boost {
+                    shed_timer: Timer::from_seconds(BOOST_SHED_SECONDS, TimerMode::Repeating),
+                });
+                speed.0 = config.speed * BOOST_MULTIPLIER;
+                changed = true;
+            }
+            (false, Some(_)) => {
+                commands.entity(snake).remove::<Boost>();
+                speed.0 = config.speed;
+                changed = true;
+            }
+            (false, None) => {}
+        }
+    }
+    if changed {
+        // Tell peers about the new speed right away instead of waiting for the next sync.
+        let duration = sync_timer.timer.duration();
+        sync_timer.timer.set_elapsed(duration);
+    }
+}
+
+/// Despawns up to `count` cells from the end of the snake, keeping at least
+/// [`MIN_SNAKE_CELLS`], and makes the new last cell the tail.
+pub fn shed_tail_cells(
+    commands: &mut Commands,
+    children: &Children,
+    cell_move_ids: &Query<&MoveId, With<CellTag>>,
+    moves: &mut Moves,
+    count: usize,
+) {
+    let keep = children.len().saturating_sub(count).max(MIN_SNAKE_CELLS);
+    if keep >= children.len() {
+        return;
+    }
+    for cell in children.iter().skip(keep) {
+        commands.entity(*cell).despawn_recursive();
+    }
+    let new_tail = children[keep - 1];
+    commands.entity(new_tail).insert(Tail);
+    // Moves the new tail already went past would never be cleaned up.
+    if let Ok(tail_move) = cell_move_ids.get(new_tail) {
+        moves.moves.retain(|_move| _move.0 > tail_move.0);
+    }
+}
+
 pub fn update_cell_direction(
     mut query: Query<
         (
@@ -185,7 +367,8 @@ pub fn update_cell_direction(
                     if distance_vec2.normalize() - current_dir.0 == Vec2::ZERO {
                         let extra_distance = _move.2 .0 * distance_vec2.distance(Vec2::ZERO);
                         current_dir.0 = _move.2 .0;
-                        cell.1.translation = _move.1
+                        // Moves with a target are jumps: the cell carries on from there.
+                        cell.1.translation = _move.3.unwrap_or(_move.1)
                             + Vec3 {
                                 x: extra_distance.x,
                                 y: extra_distance.y,
@@ -300,7 +483,7 @@ pub fn update_head_sensor(
 //                         move_id: MoveId(tail.3 .0),
 //                         sprite: SpriteBundle {
 //                             sprite: Sprite {
-//                                 color: player.color,
+//                                 color: player.snake_color(),
 //                                 custom_size: Some(Vec2::new(
 //                                     config.cell_size.0,
 //                                     config.cell_size.1,
@@ -320,3 +503,53 @@ pub fn update_head_sensor(
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{arena::ArenaShape, game_mode::GameMode};
+
+    const MARGIN: f32 = 100.0;
+
+    #[test]
+    fn spawns_inside_the_margin() {
+        let rules = GameMode::FreePlay.rules();
+        let mut rng = StdRng::seed_from_u64(1);
+        let arena = Arena::default();
+        for _ in 0..100 {
+            let (position, direction) = pick_spawn_with(&mut rng, &arena, rules, MARGIN, &[]);
+            assert!(arena.contains(position, MARGIN - 1.0));
+            assert!(CARDINALS.contains(&direction));
+        }
+    }
+
+    #[test]
+    fn keeps_clear_of_hazards() {
+        let rules = GameMode::FreePlay.rules();
+        let mut rng = StdRng::seed_from_u64(2);
+        let arena = Arena::default();
+        for _ in 0..100 {
+            let (position, _) = pick_spawn_with(&mut rng, &arena, rules, MARGIN, &[Vec2::ZERO]);
+            assert!(position.length() >= SPAWN_CLEARANCE);
+        }
+    }
+
+    #[test]
+    fn heads_for_the_middle_when_nowhere_ahead_is_inside() {
+        let rules = GameMode::FreePlay.rules();
+        let mut rng = StdRng::seed_from_u64(3);
+        let arena = Arena {
+            shape: ArenaShape::Rectangle {
+                half_size: Vec2::splat(200.0),
+            },
+            ..default()
+        };
+        for _ in 0..100 {
+            let (position, direction) = pick_spawn_with(&mut rng, &arena, rules, MARGIN, &[]);
+            assert!(CARDINALS.contains(&direction));
+            assert!(direction.dot(-position) >= 0.0);
+        }
+    }
+}
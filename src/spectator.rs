@@ -0,0 +1,217 @@
+use bevy::prelude::*;
+
+use crate::{
+    arena::Arena,
+    chat::Chat,
+    networking::{ConnectionState, PlayersChanged, SendMessage, TransportMessage},
+    terrain::FreeCamera,
+    CellTag, MainCamera, SnakeTag,
+};
+
+/// Whether this machine only watches, picked on the entry menu or in the lobby.
+#[derive(Resource, Default)]
+pub struct Spectate(pub bool);
+
+/// What a spectator's camera is pointed at.
+#[derive(Resource, Default)]
+pub struct SpectatorView {
+    /// The player being followed, or `None` for the whole arena.
+    following: Option<u32>,
+}
+
+#[derive(Component)]
+pub struct SpectatorStatusText;
+
+/// Puts [`Spectate`] into our own [`PlayerProp`] and tells the room when it changes.
+///
+/// [`PlayerProp`]: crate::networking::PlayerProp
+pub fn sync_spectating(
+    spectate: Res<Spectate>,
+    mut connection_handler: ResMut<ConnectionState>,
+    mut players_changed: EventWriter<PlayersChanged>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_mut() else {
+        return;
+    };
+    let Some(self_id) = connection.self_id else {
+        return;
+    };
+    let Some(player) = connection.players.iter_mut().find(|p| p.user_id == self_id) else {
+        return;
+    };
+    if player.spectator == spectate.0 {
+        return;
+    }
+    player.spectator = spectate.0;
+    if let Err(err) =
+        connection
+            .sender
+            .send(SendMessage::TransportMessage(TransportMessage::Spectating(
+                spectate.0,
+            )))
+    {
+        warn!("{err:?}")
+    }
+    players_changed.send(PlayersChanged {
+        players: connection.players.clone(),
+        self_player: connection.self_id,
+    });
+}
+
+/// Left and right switch between the players' snakes, space zooms out to the whole arena.
+pub fn spectator_camera(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut view: ResMut<SpectatorView>,
+    mut camera: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut OrthographicProjection,
+            &Camera,
+            Option<&FreeCamera>,
+        ),
+        With<MainCamera>,
+    >,
+    snakes: Query<(&SnakeTag, &Children)>,
+    heads: Query<&GlobalTransform, With<CellTag>>,
+    arena: Res<Arena>,
+    spectate: Res<Spectate>,
+    chat: Res<Chat>,
+) {
+    let Ok((entity, mut transform, mut projection, camera, free)) = camera.get_single_mut() else {
+        return;
+    };
+    if !spectate.0 {
+        return;
+    }
+    // The terrain follows a camera without a snake of its own only if it is free.
+    if free.is_none() {
+        commands.entity(entity).insert(FreeCamera);
+    }
+
+    let mut watched: Vec<u32> = snakes
+        .iter()
+        .filter_map(|(tag, _)| match tag {
+            SnakeTag::OtherPlayerSnake(id) => Some(*id),
+            SnakeTag::SelfPlayerSnake => None,
+        })
+        .collect();
+    watched.sort();
+    let typing = chat.is_typing();
+    let step = if typing {
+        None
+    } else if keys.just_pressed(KeyCode::Right) {
+        Some(1)
+    } else if keys.just_pressed(KeyCode::Left) {
+        Some(watched.len().saturating_sub(1))
+    } else {
+        None
+    };
+    if !typing && keys.just_pressed(KeyCode::Space) {
+        view.following = None;
+    } else if let (Some(step), false) = (step, watched.is_empty()) {
+        let index = view
+            .following
+            .and_then(|id| watched.iter().position(|watched| *watched == id))
+            .map_or(0, |index| (index + step) % watched.len());
+        view.following = Some(watched[index]);
+    }
+
+    match view.following {
+        Some(user_id) => {
+            // Stays where the snake died until it is back.
+            let head = snakes
+                .iter()
+                .find(|(tag, _)| **tag == SnakeTag::OtherPlayerSnake(user_id))
+                .and_then(|(_, cells)| cells.iter().find_map(|cell| heads.get(*cell).ok()));
+            if let Some(head) = head {
+                transform.translation.x = head.translation().x;
+                transform.translation.y = head.translation().y;
+            }
+            if projection.scale != 1.0 {
+                projection.scale = 1.0;
+            }
+        }
+        None => {
+            let Some(size) = camera.logical_viewport_size() else {
+                return;
+            };
+            let fit = (arena.shape.half_extents() * 2.2 / size)
+                .max_element()
+                .max(1.0);
+            transform.translation.x = 0.0;
+            transform.translation.y = 0.0;
+            if projection.scale != fit {
+                projection.scale = fit;
+            }
+        }
+    }
+}
+
+pub fn display_spectator_status(
+    mut commands: Commands,
+    view: Res<SpectatorView>,
+    spectate: Res<Spectate>,
+    connection_handler: Res<ConnectionState>,
+    mut text: Query<&mut Text, With<SpectatorStatusText>>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    if !spectate.0 {
+        return;
+    }
+    let watching = match view.following {
+        Some(user_id) => connection
+            .players
+            .iter()
+            .find(|p| p.user_id == user_id)
+            .map_or_else(|| format!("Player {user_id}"), |p| p.name()),
+        None => "the whole arena".to_string(),
+    };
+    let status = format!("Watching {watching}  -  Left/Right switch player, Space whole arena");
+    match text.get_single_mut() {
+        Ok(mut text) => {
+            if let Some(section) = text.sections.first_mut() {
+                if section.value != status {
+                    section.value = status;
+                }
+            }
+        }
+        Err(_) => {
+            commands.spawn((
+                SpectatorStatusText,
+                TextBundle::from_section(
+                    status,
+                    TextStyle {
+                        font_size: 20.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(10.),
+                    left: Val::Px(10.),
+                    ..default()
+                }),
+            ));
+        }
+    }
+}
+
+/// Zooms back in and hands the camera back to the snake for the next match.
+pub fn reset_spectator_camera(
+    mut commands: Commands,
+    mut camera: Query<(Entity, &mut OrthographicProjection), With<MainCamera>>,
+    spectate: Res<Spectate>,
+) {
+    if !spectate.0 {
+        return;
+    }
+    for (entity, mut projection) in camera.iter_mut() {
+        projection.scale = 1.0;
+        commands.entity(entity).remove::<FreeCamera>();
+    }
+}
@@ -0,0 +1,523 @@
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::{camera::Viewport, view::RenderLayers},
+    window::PrimaryWindow,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chat::Chat,
+    effects::ActiveEffects,
+    game_mode::CurrentMatch,
+    networking::{
+        player_color, ConnectionHandler, ConnectionState, PingTimer, PlayerProp, PlayersChanged,
+        SendMessage, TransportMessage,
+    },
+    profile::Profile,
+    snek::SpawnSnake,
+    GameStates, Head, MainCamera, Player, SnakeTag,
+};
+
+/// Layer only the main camera draws, for things that belong to the owner of the machine.
+pub const MAIN_LAYER: u8 = 1;
+
+/// Layer nothing is drawn on, so the overlay camera only adds the UI.
+const OVERLAY_LAYER: u8 = 31;
+
+/// Local players get user ids from here up, below the bots' and well clear of the ones the
+/// relay hands out.
+pub const LOCAL_ID_BASE: u32 = 1 << 30;
+
+/// Keys a local player steers with. The arrows stay with whoever owns the machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeySet {
+    Wasd,
+    Ijkl,
+    Numpad,
+}
+
+impl KeySet {
+    pub const ALL: [KeySet; 3] = [KeySet::Wasd, KeySet::Ijkl, KeySet::Numpad];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeySet::Wasd => "WASD",
+            KeySet::Ijkl => "IJKL",
+            KeySet::Numpad => "Numpad",
+        }
+    }
+
+    /// Up, down, left and right.
+    pub fn keys(&self) -> [KeyCode; 4] {
+        match self {
+            KeySet::Wasd => [KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D],
+            KeySet::Ijkl => [KeyCode::I, KeyCode::K, KeyCode::J, KeyCode::L],
+            KeySet::Numpad => [
+                KeyCode::Numpad8,
+                KeyCode::Numpad5,
+                KeyCode::Numpad4,
+                KeyCode::Numpad6,
+            ],
+        }
+    }
+
+    /// Boosts like space does for the arrows, and brings the snake back after dying.
+    pub fn boost_key(&self) -> KeyCode {
+        match self {
+            KeySet::Wasd => KeyCode::Q,
+            KeySet::Ijkl => KeyCode::U,
+            KeySet::Numpad => KeyCode::Numpad0,
+        }
+    }
+
+    fn boost_label(&self) -> &'static str {
+        match self {
+            KeySet::Wasd => "Q",
+            KeySet::Ijkl => "U",
+            KeySet::Numpad => "Numpad 0",
+        }
+    }
+}
+
+/// Where a local player sits: at the keyboard of `owner`, steering with `keys`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Seat {
+    pub owner: u32,
+    pub keys: KeySet,
+}
+
+/// User id of whoever uses `keys` at the keyboard of `owner`, the same on every peer.
+pub fn local_player_id(owner: u32, keys: KeySet) -> u32 {
+    LOCAL_ID_BASE + owner % (LOCAL_ID_BASE / 4) * 4 + keys as u32
+}
+
+/// Whether `user_id` is one of the players sharing the keyboard of `owner`.
+pub fn is_local_player_of(user_id: u32, owner: u32) -> bool {
+    KeySet::ALL
+        .into_iter()
+        .any(|keys| local_player_id(owner, keys) == user_id)
+}
+
+/// Key sets of the players sharing our keyboard, in the order they joined.
+pub fn own_seats(connection: &ConnectionHandler) -> Vec<KeySet> {
+    connection
+        .players
+        .iter()
+        .filter_map(|player| player.seat)
+        .filter(|seat| Some(seat.owner) == connection.self_id)
+        .map(|seat| seat.keys)
+        .collect()
+}
+
+/// The first key set nobody at our keyboard has taken yet.
+pub fn free_key_set(connection: &ConnectionHandler) -> Option<KeySet> {
+    let taken = own_seats(connection);
+    KeySet::ALL.into_iter().find(|keys| !taken.contains(keys))
+}
+
+/// Brings the players at the keyboard of `owner` in line with `seats`, naming them after the
+/// owner, and returns the ids of the ones that left.
+pub fn sync_local_players(players: &mut Vec<PlayerProp>, owner: u32, seats: &[KeySet]) -> Vec<u32> {
+    let removed: Vec<u32> = players
+        .iter()
+        .filter(|player| {
+            player
+                .seat
+                .is_some_and(|seat| seat.owner == owner && !seats.contains(&seat.keys))
+        })
+        .map(|player| player.user_id)
+        .collect();
+    players.retain(|player| !removed.contains(&player.user_id));
+    let Some(owner_name) = players
+        .iter()
+        .find(|player| player.user_id == owner)
+        .map(|player| player.name())
+    else {
+        return removed;
+    };
+    for (index, keys) in seats.iter().enumerate() {
+        let user_id = local_player_id(owner, *keys);
+        let name = format!("{owner_name} {}", index + 2);
+        match players.iter_mut().find(|player| player.user_id == user_id) {
+            Some(player) => player.profile.name = name,
+            None => players.push(PlayerProp {
+                last_update_time: None,
+                start_time: None,
+                user_id,
+                color: player_color(user_id),
+                score: 0,
+                highest_score: 0,
+                team: None,
+                bot: None,
+                seat: Some(Seat { owner, keys: *keys }),
+                spectator: false,
+                ready: false,
+                profile: Profile {
+                    name,
+                    ..Profile::default()
+                },
+            }),
+        }
+    }
+    removed
+}
+
+/// Seats someone else at our keyboard on the first free keys and tells everyone.
+pub fn add_local_player(connection: &mut ConnectionHandler) {
+    let (Some(self_id), Some(keys)) = (connection.self_id, free_key_set(connection)) else {
+        return;
+    };
+    let mut seats = own_seats(connection);
+    seats.push(keys);
+    sync_local_players(&mut connection.players, self_id, &seats);
+    send_local_players(connection, seats);
+}
+
+/// Lets the last player who joined our keyboard go and tells everyone.
+pub fn remove_local_player(connection: &mut ConnectionHandler) {
+    let Some(self_id) = connection.self_id else {
+        return;
+    };
+    let mut seats = own_seats(connection);
+    if seats.pop().is_none() {
+        return;
+    }
+    sync_local_players(&mut connection.players, self_id, &seats);
+    send_local_players(connection, seats);
+}
+
+fn send_local_players(connection: &ConnectionHandler, seats: Vec<KeySet>) {
+    if let Err(err) = connection.sender.send(SendMessage::TransportMessage(
+        TransportMessage::LocalPlayers(seats),
+    )) {
+        warn!("{err:?}")
+    }
+}
+
+/// Repeats who shares our keyboard with every ping, for peers joining later, and keeps their
+/// names in line with ours.
+pub fn announce_local_players(
+    ping_timer: Res<PingTimer>,
+    mut connection_handler: ResMut<ConnectionState>,
+    mut players_changed: EventWriter<PlayersChanged>,
+) {
+    if !ping_timer.timer.just_finished() {
+        return;
+    }
+    let ConnectionState::Connected(connection) = connection_handler.as_mut() else {
+        return;
+    };
+    let Some(self_id) = connection.self_id else {
+        return;
+    };
+    let seats = own_seats(connection);
+    let before = connection.players.clone();
+    sync_local_players(&mut connection.players, self_id, &seats);
+    if connection.players != before {
+        players_changed.send(PlayersChanged {
+            players: connection.players.clone(),
+            self_player: connection.self_id,
+        });
+    }
+    send_local_players(connection, seats);
+}
+
+/// Brings back the snake of a local player who presses their boost key after dying, in modes
+/// that let snakes respawn.
+pub fn respawn_local_players(
+    keys: Res<Input<KeyCode>>,
+    chat: Res<Chat>,
+    snakes: Query<&SnakeTag>,
+    current_match: Res<CurrentMatch>,
+    connection_handler: Res<ConnectionState>,
+    mut spawn_snek_writer: EventWriter<SpawnSnake>,
+) {
+    if chat.is_typing() || !current_match.settings.mode.rules().allows_respawn() {
+        return;
+    }
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let Some(self_id) = connection.self_id else {
+        return;
+    };
+    for seat in own_seats(connection) {
+        let tag = SnakeTag::OtherPlayerSnake(local_player_id(self_id, seat));
+        if keys.just_pressed(seat.boost_key()) && !snakes.iter().any(|snake| *snake == tag) {
+            spawn_snek_writer.send(SpawnSnake { keys: Some(seat) });
+        }
+    }
+}
+
+/// Camera following the snake of the local player with this user id.
+#[derive(Component)]
+pub struct LocalCamera(pub u32);
+
+/// Full-window camera drawing the UI while the window is split.
+#[derive(Component)]
+pub struct OverlayCamera;
+
+/// Name, score and effects in the corner of the pane of the player with this user id.
+#[derive(Component)]
+pub struct PaneLabel(u32);
+
+/// User ids of the players sharing our keyboard.
+fn local_players(connection: &ConnectionHandler) -> Vec<u32> {
+    let Some(self_id) = connection.self_id else {
+        return vec![];
+    };
+    own_seats(connection)
+        .into_iter()
+        .map(|keys| local_player_id(self_id, keys))
+        .collect()
+}
+
+/// Pane `index` of `count`: side by side for two, a 2x2 grid for more, the last pane taking
+/// what is left of its row.
+fn pane(index: usize, count: usize, window: UVec2) -> Viewport {
+    let columns = count.min(2) as u32;
+    let rows = (count as u32).div_ceil(2);
+    let index = index as u32;
+    let size = UVec2::new(window.x / columns, window.y / rows);
+    let position = UVec2::new(index % columns * size.x, index / columns * size.y);
+    let width = if index + 1 == count as u32 {
+        window.x - position.x
+    } else {
+        size.x
+    };
+    Viewport {
+        physical_position: position,
+        physical_size: UVec2::new(width, size.y),
+        ..default()
+    }
+}
+
+/// Only touches the camera when the pane actually moved, so its projection isn't redone every
+/// frame.
+fn set_viewport(camera: &mut Mut<Camera>, viewport: Option<Viewport>) {
+    let rect = |viewport: &Option<Viewport>| {
+        viewport
+            .as_ref()
+            .map(|viewport| (viewport.physical_position, viewport.physical_size))
+    };
+    if rect(&camera.viewport) != rect(&viewport) {
+        camera.viewport = viewport;
+    }
+}
+
+/// Gives every local player a pane of their own while playing, and puts the window back
+/// together when they leave.
+pub fn sync_split_screen(
+    mut commands: Commands,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut main_camera: Query<(Entity, &mut Camera), (With<MainCamera>, Without<LocalCamera>)>,
+    mut local_cameras: Query<(Entity, &LocalCamera, &mut Camera), Without<MainCamera>>,
+    overlay: Query<Entity, With<OverlayCamera>>,
+    labels: Query<(Entity, &PaneLabel)>,
+    state: Res<State<GameStates>>,
+    connection_handler: Res<ConnectionState>,
+) {
+    let (Ok(window), Ok((main_entity, mut main_camera))) =
+        (window.get_single(), main_camera.get_single_mut())
+    else {
+        return;
+    };
+    let (self_id, local) = match connection_handler.as_ref() {
+        ConnectionState::Connected(connection) if *state.get() == GameStates::GamePlay => {
+            (connection.self_id, local_players(connection))
+        }
+        _ => (None, vec![]),
+    };
+    let split = !local.is_empty();
+    let count = local.len() + 1;
+    let size = UVec2::new(window.physical_width(), window.physical_height());
+
+    set_viewport(&mut main_camera, split.then(|| pane(0, count, size)));
+    for (camera, local_camera, mut camera_settings) in local_cameras.iter_mut() {
+        match local.iter().position(|id| *id == local_camera.0) {
+            Some(index) => set_viewport(&mut camera_settings, Some(pane(index + 1, count, size))),
+            None => commands.entity(camera).despawn_recursive(),
+        }
+    }
+    for (index, user_id) in local.iter().enumerate() {
+        if local_cameras
+            .iter()
+            .any(|(_, camera, _)| camera.0 == *user_id)
+        {
+            continue;
+        }
+        commands.spawn((
+            LocalCamera(*user_id),
+            UiCameraConfig { show_ui: false },
+            RenderLayers::from_layers(&[0, pane_layer(index + 1)]),
+            Camera2dBundle {
+                camera: Camera {
+                    order: index as isize + 1,
+                    viewport: Some(pane(index + 1, count, size)),
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+    }
+    for (label, pane_label) in labels.iter() {
+        if !split || !(local.contains(&pane_label.0) || self_id == Some(pane_label.0)) {
+            commands.entity(label).despawn_recursive();
+        }
+    }
+
+    // The UI is laid out over the whole window, so it gets a camera of its own.
+    match (split, overlay.get_single()) {
+        (true, Err(_)) => {
+            commands
+                .entity(main_entity)
+                .insert(UiCameraConfig { show_ui: false });
+            commands.spawn((
+                OverlayCamera,
+                RenderLayers::layer(OVERLAY_LAYER),
+                Camera2dBundle {
+                    // Drawn after every pane.
+                    camera: Camera {
+                        order: KeySet::ALL.len() as isize + 1,
+                        ..default()
+                    },
+                    camera_2d: Camera2d {
+                        clear_color: ClearColorConfig::None,
+                    },
+                    ..default()
+                },
+            ));
+        }
+        (false, Ok(overlay)) => {
+            commands
+                .entity(main_entity)
+                .insert(UiCameraConfig { show_ui: true });
+            commands.entity(overlay).despawn_recursive();
+        }
+        _ => {}
+    }
+}
+
+fn pane_layer(index: usize) -> u8 {
+    MAIN_LAYER + index as u8
+}
+
+/// Points every local player's camera at their own snake's head.
+pub fn follow_local_players(
+    mut cameras: Query<(&mut Transform, &LocalCamera)>,
+    snakes: Query<(&SnakeTag, &Children), With<Player>>,
+    heads: Query<&Transform, (With<Head>, Without<LocalCamera>)>,
+) {
+    for (mut camera, local_camera) in cameras.iter_mut() {
+        let tag = SnakeTag::OtherPlayerSnake(local_camera.0);
+        let Some(head) = snakes
+            .iter()
+            .find(|(snake, _)| **snake == tag)
+            .and_then(|(_, children)| children.iter().find_map(|cell| heads.get(*cell).ok()))
+        else {
+            continue;
+        };
+        camera.translation.x = head.translation.x;
+        camera.translation.y = head.translation.y;
+    }
+}
+
+/// Keeps the name, score and effects of each player in the top left corner of their pane, or
+/// how to come back once their snake died.
+pub fn display_pane_labels(
+    mut commands: Commands,
+    cameras: Query<
+        (&Transform, &Camera, &RenderLayers, Option<&LocalCamera>),
+        (
+            Or<(With<MainCamera>, With<LocalCamera>)>,
+            Without<PaneLabel>,
+        ),
+    >,
+    mut labels: Query<(&mut Transform, &mut Text, &PaneLabel)>,
+    snakes: Query<(&SnakeTag, &ActiveEffects)>,
+    current_match: Res<CurrentMatch>,
+    connection_handler: Res<ConnectionState>,
+) {
+    let ConnectionState::Connected(connection) = connection_handler.as_ref() else {
+        return;
+    };
+    let Some(self_id) = connection.self_id else {
+        return;
+    };
+    if cameras
+        .iter()
+        .all(|(.., local_camera)| local_camera.is_none())
+    {
+        return;
+    }
+    for (camera_transform, camera, layers, local_camera) in cameras.iter() {
+        let user_id = local_camera.map_or(self_id, |local_camera| local_camera.0);
+        let Some(player_prop) = connection.players.iter().find(|p| p.user_id == user_id) else {
+            continue;
+        };
+        let tag = if user_id == self_id {
+            SnakeTag::SelfPlayerSnake
+        } else {
+            SnakeTag::OtherPlayerSnake(user_id)
+        };
+        let effects = snakes
+            .iter()
+            .find(|(snake, _)| **snake == tag)
+            .map(|(_, effects)| effects);
+        let mut value = format!("{}  {}", player_prop.name(), player_prop.score);
+        match (effects, player_prop.seat) {
+            (Some(effects), _) => {
+                for effect in effects.effects.iter() {
+                    value += &format!("\n{} {:.0}s", effect.kind.label(), effect.remaining.ceil());
+                }
+            }
+            // The owner gets the respawn menu instead.
+            (None, None) => {}
+            (None, Some(seat)) => {
+                if current_match.settings.mode.rules().allows_respawn() {
+                    value += &format!("\nPress {} to respawn", seat.keys.boost_label());
+                } else {
+                    value += "\nEliminated";
+                }
+            }
+        }
+        let Some(size) = camera.logical_viewport_size() else {
+            continue;
+        };
+        let translation = (camera_transform.translation.truncate()
+            + Vec2::new(-size.x / 2.0 + 10.0, size.y / 2.0 - 10.0))
+        .extend(10.0);
+
+        match labels.iter_mut().find(|(_, _, label)| label.0 == user_id) {
+            Some((mut transform, mut text, _)) => {
+                transform.translation = translation;
+                if let Some(section) = text.sections.first_mut() {
+                    if section.value != value {
+                        section.value = value;
+                    }
+                }
+            }
+            None => {
+                commands.spawn((
+                    PaneLabel(user_id),
+                    // Only the pane's own camera shows it.
+                    layers.without(0),
+                    Text2dBundle {
+                        text: Text::from_section(
+                            value,
+                            TextStyle {
+                                font_size: 24.0,
+                                color: player_prop.snake_color(),
+                                ..default()
+                            },
+                        ),
+                        text_anchor: bevy::sprite::Anchor::TopLeft,
+                        transform: Transform::from_translation(translation),
+                        ..default()
+                    },
+                ));
+            }
+        }
+    }
+}
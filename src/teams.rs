@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+
+use crate::{
+    game_mode::{CurrentMatch, TeamSettings, TEAM_COLORS, TEAM_NAMES},
+    networking::{
+        ConnectionHandler, ConnectionState, PlayerProp, PlayersChanged, SendMessage,
+        TransportMessage,
+    },
+    Host,
+};
+
+pub fn team_color(team: u8) -> Color {
+    TEAM_COLORS[team as usize % TEAM_COLORS.len()]
+}
+
+pub fn team_name(team: u8) -> &'static str {
+    TEAM_NAMES[team as usize % TEAM_NAMES.len()]
+}
+
+/// Whether two different players play on the same team.
+pub fn are_teammates(connection: &ConnectionHandler, a: u32, b: u32) -> bool {
+    let team = |id: u32| {
+        connection
+            .players
+            .iter()
+            .find(|p| p.user_id == id)
+            .and_then(|p| p.team)
+    };
+    a != b && team(a).is_some() && team(a) == team(b)
+}
+
+/// Summed scores of every team, from the highest down.
+pub fn team_scores(players: &[PlayerProp]) -> Vec<(u8, u32)> {
+    let mut scores: Vec<(u8, u32)> = vec![];
+    for player in players.iter() {
+        let Some(team) = player.team else {
+            continue;
+        };
+        match scores.iter_mut().find(|(t, _)| *t == team) {
+            Some((_, score)) => *score += player.score,
+            None => scores.push((team, player.score)),
+        }
+    }
+    scores.sort_by_key(|(team, score)| (std::cmp::Reverse(*score), *team));
+    scores
+}
+
+fn smallest_team(players: &[PlayerProp], count: u8) -> u8 {
+    (0..count)
+        .min_by_key(|team| players.iter().filter(|p| p.team == Some(*team)).count())
+        .unwrap_or(0)
+}
+
+/// Keeps every player on a team while teams are on, and tells everyone who plays where.
+///
+/// Changing the number of teams in the lobby deals everyone out again; players joining
+/// later go to whichever team is smallest.
+pub fn assign_teams(
+    host: Query<&Host>,
+    current_match: Res<CurrentMatch>,
+    mut connection_handler: ResMut<ConnectionState>,
+    mut players_changed: EventWriter<PlayersChanged>,
+    mut last_settings: Local<Option<TeamSettings>>,
+) {
+    if host.is_empty() {
+        return;
+    }
+    let ConnectionState::Connected(connection) = connection_handler.as_mut() else {
+        return;
+    };
+    let teams = current_match.settings.teams;
+    let rebalance = *last_settings != Some(teams) && current_match.started_at.is_none();
+    *last_settings = Some(teams);
+
+    let mut changed = false;
+    if rebalance {
+        for (index, player) in connection
+            .players
+            .iter_mut()
+            .filter(|p| !p.spectator)
+            .enumerate()
+        {
+            let team = teams
+                .enabled()
+                .then_some((index % teams.count as usize) as u8);
+            if player.team != team {
+                player.team = team;
+                changed = true;
+            }
+        }
+    }
+    for index in 0..connection.players.len() {
+        // Spectators stay out of the teams.
+        let enabled = teams.enabled() && !connection.players[index].spectator;
+        let team = match (enabled, connection.players[index].team) {
+            (false, None) => continue,
+            (true, Some(team)) if team < teams.count => continue,
+            (false, Some(_)) => None,
+            (true, _) => Some(smallest_team(&connection.players, teams.count)),
+        };
+        connection.players[index].team = team;
+        changed = true;
+    }
+    if !changed {
+        return;
+    }
+    if let Err(err) = connection.sender.send(SendMessage::TransportMessage(
+        TransportMessage::TeamAssignments(team_assignments(connection)),
+    )) {
+        warn!("{err:?}")
+    }
+    players_changed.send(PlayersChanged {
+        players: connection.players.clone(),
+        self_player: connection.self_id,
+    });
+}
+
+pub fn team_assignments(connection: &ConnectionHandler) -> Vec<(u32, Option<u8>)> {
+    connection
+        .players
+        .iter()
+        .map(|player| (player.user_id, player.team))
+        .collect()
+}
@@ -6,7 +6,9 @@ use bevy::{
     sprite::{Material2d, MaterialMesh2dBundle, Mesh2dHandle},
 };
 
-use crate::{GameConfig, Head};
+use crate::{
+    arena::Arena, split_screen::LocalCamera, GameConfig, Head, MainCamera, Player, SnakeTag,
+};
 
 #[derive(AsBindGroup, TypeUuid, Clone, TypePath)]
 #[uuid = "1e449d2e-6901-4bff-95fa-d7407ad62b58"]
@@ -29,6 +31,14 @@ pub struct TerrainMaterial {
     #[texture(7)]
     #[sampler(8)]
     water_texture: Handle<Image>,
+
+    /// See [`Arena::shader_params`].
+    #[uniform(9)]
+    arena: Vec4,
+
+    /// x: width of the band along the edge that is about to be lost.
+    #[uniform(10)]
+    arena_warning: Vec4,
 }
 
 #[derive(Resource, Clone)]
@@ -48,6 +58,7 @@ pub fn setup_terrain(
     server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut terrain_materials: ResMut<Assets<TerrainMaterial>>,
+    arena: Res<Arena>,
 ) {
     let material = terrain_materials.add(TerrainMaterial {
         params: vec4(0.1, 2.8, 14.0, rand::random()),
@@ -55,6 +66,8 @@ pub fn setup_terrain(
         dirt_texture: server.load("dirt_02.jpeg"),
         grass_texture2: server.load("grass_01.jpeg"),
         water_texture: server.load("tex_Water.jpg"),
+        arena: arena.shader_params(),
+        arena_warning: Vec4::new(arena.warning_margin, 0.0, 0.0, 0.0),
     });
     let mesh = meshes.add(Mesh::from(shape::Quad::new(Vec2::new(100.0, 100.0))));
     commands.insert_resource(TerrainMeshProp {
@@ -63,74 +76,134 @@ pub fn setup_terrain(
     });
 }
 
+/// Keeps the ground tiled around whatever each pane is looking at.
 pub fn terrain_tiler(
     mut commands: Commands,
     terrains: Query<(Entity, &Terrain)>,
-    player_head: Query<&Transform, With<Head>>,
+    snakes: Query<&SnakeTag>,
+    main_camera: Query<
+        (&Transform, &OrthographicProjection, Option<&FreeCamera>),
+        With<MainCamera>,
+    >,
+    local_cameras: Query<(&Transform, &OrthographicProjection), With<LocalCamera>>,
     terrain_prop: Res<TerrainMeshProp>,
     config: Res<GameConfig>,
 ) {
-    if let Some(head) = player_head.iter().next() {
-        let block_x = head.translation.x as i32 / 100;
-        let block_y = head.translation.y as i32 / 100;
-        let horizontal = (config.game_size.0 as i32) / 200 + 3;
-        let vertical = (config.game_size.1 as i32) / 200 + 3;
-
-        for terr in terrains.iter() {
-            if !((block_x - horizontal)..(block_x + horizontal)).contains(&terr.1.x)
-                || !((block_y - vertical)..(block_y + vertical)).contains(&terr.1.y)
-            {
-                commands.entity(terr.0).despawn_recursive();
-            }
+    let alive = snakes
+        .iter()
+        .any(|snake| *snake == SnakeTag::SelfPlayerSnake);
+    let cameras: Vec<(&Transform, &OrthographicProjection)> = main_camera
+        .iter()
+        .filter(|(.., free)| free.is_some() || alive)
+        .map(|(camera, projection, _)| (camera, projection))
+        .chain(local_cameras.iter())
+        .collect();
+    let centers: Vec<(i32, i32)> = cameras
+        .iter()
+        .map(|(camera, _)| {
+            (
+                camera.translation.x as i32 / 100,
+                camera.translation.y as i32 / 100,
+            )
+        })
+        .collect();
+    if centers.is_empty() {
+        return;
+    }
+    // A zoomed out camera sees further.
+    let scale = cameras
+        .iter()
+        .map(|(_, projection)| projection.scale)
+        .fold(1.0, f32::max);
+    let horizontal = (config.game_size.0 as f32 * scale) as i32 / 200 + 3;
+    let vertical = (config.game_size.1 as f32 * scale) as i32 / 200 + 3;
+
+    for terr in terrains.iter() {
+        if !centers.iter().any(|(block_x, block_y)| {
+            ((block_x - horizontal)..(block_x + horizontal)).contains(&terr.1.x)
+                && ((block_y - vertical)..(block_y + vertical)).contains(&terr.1.y)
+        }) {
+            commands.entity(terr.0).despawn_recursive();
         }
+    }
+    let mut spawned: Vec<(i32, i32)> = vec![];
+    for (block_x, block_y) in centers.iter() {
         for x in -horizontal..horizontal {
             for y in -vertical..vertical {
                 let block_x = block_x + x;
                 let block_y = block_y + y;
-                if !terrains
+                if terrains
                     .iter()
                     .any(|f| f.1.x == block_x && f.1.y == block_y)
+                    || spawned.contains(&(block_x, block_y))
                 {
-                    commands
-                        .spawn((
-                            Terrain {
-                                x: block_x,
-                                y: block_y,
-                            },
-                            TransformBundle::from_transform(Transform::from_translation(Vec3 {
-                                x: (block_x * 100) as f32,
-                                y: (block_y * 100) as f32,
-                                z: 0.0,
-                            })),
-                            VisibilityBundle {
-                                ..Default::default()
-                            },
-                        ))
-                        .with_children(|terr| {
-                            terr.spawn(MaterialMesh2dBundle {
-                                mesh: terrain_prop.mesh.clone(),
-                                material: terrain_prop.material.clone(),
-                                ..Default::default()
-                            });
-                        });
+                    continue;
                 }
+                spawned.push((block_x, block_y));
+                commands
+                    .spawn((
+                        Terrain {
+                            x: block_x,
+                            y: block_y,
+                        },
+                        TransformBundle::from_transform(Transform::from_translation(Vec3 {
+                            x: (block_x * 100) as f32,
+                            y: (block_y * 100) as f32,
+                            z: 0.0,
+                        })),
+                        VisibilityBundle {
+                            ..Default::default()
+                        },
+                    ))
+                    .with_children(|terr| {
+                        terr.spawn(MaterialMesh2dBundle {
+                            mesh: terrain_prop.mesh.clone(),
+                            material: terrain_prop.material.clone(),
+                            ..Default::default()
+                        });
+                    });
             }
         }
     }
 }
 
+pub fn sync_terrain_arena(
+    arena: Res<Arena>,
+    terrain_prop: Res<TerrainMeshProp>,
+    mut terrain_materials: ResMut<Assets<TerrainMaterial>>,
+) {
+    if !arena.is_changed() {
+        return;
+    }
+    if let Some(material) = terrain_materials.get_mut(&terrain_prop.material) {
+        material.arena = arena.shader_params();
+        material.arena_warning = Vec4::new(arena.warning_margin, 0.0, 0.0, 0.0);
+    }
+}
+
 #[derive(Component)]
 pub struct Terrain {
     x: i32,
     y: i32,
 }
 
+/// Marks a main camera the viewer moves around by hand rather than one following a snake.
+#[derive(Component)]
+pub struct FreeCamera;
+
+/// Keeps the main camera on our own snake's head. The cameras of other players at this
+/// machine are moved by [`follow_local_players`](crate::split_screen::follow_local_players).
 pub fn sync_cam(
     mut transforms: Query<&mut Transform>,
-    head: Query<Entity, With<Head>>,
-    camera: Query<Entity, With<Camera>>,
+    snakes: Query<(&Player, &Children)>,
+    heads: Query<Entity, With<Head>>,
+    camera: Query<Entity, With<MainCamera>>,
 ) {
-    if let (Some(head), Some(camera)) = (head.iter().next(), camera.iter().next()) {
+    let head = snakes
+        .iter()
+        .find(|(player, _)| player.keys.is_none())
+        .and_then(|(_, children)| children.iter().find_map(|cell| heads.get(*cell).ok()));
+    if let (Some(head), Some(camera)) = (head, camera.iter().next()) {
         if let Ok(head_trans) = transforms.get(head) {
             let head_transform = head_trans.translation;
             if let Ok(mut cam_trans) = transforms.get_mut(camera) {